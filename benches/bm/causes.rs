@@ -0,0 +1,75 @@
+use divan::Bencher;
+use fences::{
+    board::MoveSource, board::Task, formats::load_puzzle, rules::BoardRule, solver::*, Board,
+    BoardGeom, Cell, Fence,
+};
+
+/// A board that writes out the rule of every move it is asked to play, as
+/// moves were named before they kept just their rule and where it matched.
+struct Naming<'a> {
+    board: Board,
+    rules: &'a [BoardRule],
+}
+impl BoardGeom for Naming<'_> {
+    fn size(&self) -> (usize, usize) {
+        self.board.size()
+    }
+    fn rotate(&mut self) {
+        self.board.rotate()
+    }
+}
+impl FencesSolver for Naming<'_> {
+    fn set_solution(&mut self, solution: &str) {
+        self.board.set_solution(solution)
+    }
+    fn fences_iter(&self) -> impl Iterator<Item = (Edge, &Fence)> {
+        self.board.fences_iter()
+    }
+    fn tasks_iter(&self) -> impl Iterator<Item = (Cell, &Task)> {
+        self.board.tasks_iter()
+    }
+    fn task(&self, cell: Cell) -> &Task {
+        self.board.task(cell)
+    }
+    fn edge(&self, e: Edge) -> &Fence {
+        self.board.edge(e)
+    }
+    fn try_play(&mut self, e: Edge, val: bool, source: MoveSource) -> Result<Changed, Conflict> {
+        let name = match source {
+            MoveSource::Rule { id, .. } => format!("{}", self.rules[id]),
+            s => s.to_string(),
+        };
+        divan::black_box(name);
+        self.board.try_play(e, val, source)
+    }
+    fn snapshot(&self) -> Snapshot {
+        self.board.snapshot()
+    }
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.board.restore(snapshot)
+    }
+}
+
+fn board_30x30() -> (Board, Vec<BoardRule>) {
+    let board = load_puzzle(&std::fs::read_to_string("puzzle/example-30x30.txt").unwrap()).unwrap();
+    (board, BoardRule::default_rules())
+}
+
+#[divan::bench]
+fn solve_30x30(bencher: Bencher) {
+    let (board, rules) = board_30x30();
+    bencher
+        .with_inputs(|| board.clone())
+        .bench_values(|mut b| solve_with(&mut b, &rules).unwrap());
+}
+
+#[divan::bench]
+fn solve_30x30_naming_moves(bencher: Bencher) {
+    let (board, rules) = board_30x30();
+    bencher
+        .with_inputs(|| Naming {
+            board: board.clone(),
+            rules: &rules,
+        })
+        .bench_values(|mut b| solve_with(&mut b, &rules).unwrap());
+}
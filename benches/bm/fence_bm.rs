@@ -1,4 +1,4 @@
-use fences::{board::*, solver::*, *};
+use fences::{board::*, packed::PackedBoard, solver::*, *};
 use grid::Grid;
 use std::fmt;
 
@@ -11,18 +11,14 @@ pub struct Board1 {
 }
 impl fmt::Display for Board1 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
+        writeln!(
             f,
-            "      {}\n",
+            "      {}",
             (0..self.cols).fold("".to_string(), |acc, x| format!("{acc}{x:2}"))
         )?;
-        let b = self.cols * (self.rows() + 1);
         for (i, x) in print_board(
             &Grid::from_vec(self.tasks.clone(), self.cols),
-            &[
-                Grid::from_vec(self.fences[0..b].to_vec(), self.cols),
-                Grid::from_vec(self.fences[b..].to_vec(), self.cols + 1),
-            ],
+            &Fences::from_vec(self.rows(), self.cols, self.fences.clone()),
             true,
         )
         .lines()
@@ -38,9 +34,9 @@ impl fmt::Display for Board1 {
                 }
             )?;
         }
-        write!(
+        writeln!(
             f,
-            "      {}\n",
+            "      {}",
             (0..self.cols).fold("".to_string(), |acc, x| format!("{acc}{x:2}"))
         )?;
         Ok(())
@@ -62,31 +58,51 @@ impl FencesSolver for Board1 {
             .for_each(|(f, v)| *f = v.try_into().unwrap());
         log::info!("set_solution\n{self}");
     }
-    fn play(&mut self, direction: usize, idx: (usize, usize), value: bool, name: String) {
-        let e = self.e2id(direction, idx);
+    fn try_play(&mut self, edge: Edge, value: bool, source: MoveSource) -> Result<Changed, Conflict> {
+        let e = self.e2id(edge);
         if let Some(curr) = *self.fences[e] {
             if curr == value {
-                log::trace!("Trying to overwrite an existing fence at [{direction}][{idx:?}]");
-                return;
+                log::trace!("Trying to overwrite an existing fence at [{edge}]");
+                return Ok(Changed::Unchanged);
             }
-            log::warn!("Overwriting an existing fence {curr} with {value} by {name}")
+            return Err(Conflict {
+                edge,
+                value,
+                source,
+                by: self
+                    .moves
+                    .iter()
+                    .rfind(|m| m.edge == edge)
+                    .cloned()
+                    .map(Box::new),
+            });
         }
         *self.fences[e] = Some(value);
         let m = Move {
-            direction,
-            idx,
+            edge,
             value,
-            name,
+            source,
         };
-        log::trace!("{:?}\n{}{self}", (m.direction, m.idx, m.value), m.name);
+        log::trace!("{} {} by {}", m.edge, m.value, m.source);
         self.moves.push(m);
+        Ok(Changed::Set)
     }
-    fn edge(&self, dir: usize, idx: Idx) -> &Fence {
-        let id = self.e2id(dir, idx);
+    fn snapshot(&self) -> Snapshot {
+        Snapshot(self.moves.len())
+    }
+    fn restore(&mut self, snapshot: Snapshot) {
+        while self.moves.len() > snapshot.0 {
+            let m = self.moves.pop().unwrap();
+            let e = self.e2id(m.edge);
+            *self.fences[e] = None;
+        }
+    }
+    fn edge(&self, e: Edge) -> &Fence {
+        let id = self.e2id(e);
         &self.fences[id]
     }
-    fn task(&self, idx: Idx) -> &Task {
-        &self.tasks[self.p2id(idx)]
+    fn task(&self, cell: Cell) -> &Task {
+        &self.tasks[self.p2id(cell)]
     }
     fn fences_iter(&self) -> impl Iterator<Item = (Edge, &Fence)> {
         self.fences
@@ -94,7 +110,7 @@ impl FencesSolver for Board1 {
             .enumerate()
             .map(|(id, v)| (self.id2e(id), v))
     }
-    fn tasks_iter(&self) -> impl Iterator<Item = (Idx, &Task)> {
+    fn tasks_iter(&self) -> impl Iterator<Item = (Cell, &Task)> {
         self.tasks
             .iter()
             .enumerate()
@@ -107,27 +123,26 @@ impl Board1 {
         let b = self.cols() * (self.rows() + 1);
         let c = self.cols();
         if id < b {
-            (0, id / c, id % c)
+            Edge::horizontal(id / c, id % c)
         } else {
             let id = id - b;
-            (1, id / (c + 1), id % (c + 1))
+            Edge::vertical(id / (c + 1), id % (c + 1))
         }
     }
     #[inline]
-    pub fn p2id(&self, p: Idx) -> usize {
+    pub fn p2id(&self, p: Cell) -> usize {
         p.0 * self.cols + p.1
     }
     #[inline]
-    pub fn id2p(&self, id: usize) -> Idx {
-        (id / self.cols, id % self.cols)
+    pub fn id2p(&self, id: usize) -> Cell {
+        Cell(id / self.cols, id % self.cols)
     }
     #[inline]
-    fn e2id(&self, dir: usize, idx: (usize, usize)) -> usize {
+    fn e2id(&self, Edge(dir, row, col): Edge) -> usize {
         let c = self.cols;
         match dir {
-            0 => c * idx.0 + idx.1,
-            1 => self.cols * (self.rows() + 1) + (c + 1) * idx.0 + idx.1,
-            _ => unreachable!(),
+            Direction::Horizontal => c * row + col,
+            Direction::Vertical => self.cols * (self.rows() + 1) + (c + 1) * row + col,
         }
     }
 }
@@ -157,9 +172,9 @@ impl core::str::FromStr for Board1 {
                 if l.is_empty() {
                     continue;
                 }
-                if l.starts_with(|c| matches!(c, '0' | '1')) {
+                if l.starts_with(['0', '1']) {
                     let mut m = l.split_whitespace();
-                    let dir = m.next().unwrap().parse().unwrap();
+                    let dir = m.next().unwrap().parse::<usize>().unwrap().try_into()?;
                     let row = m.next().unwrap().parse().unwrap();
                     let col = m.next().unwrap().parse().unwrap();
                     let val = match m.next().unwrap() {
@@ -167,7 +182,7 @@ impl core::str::FromStr for Board1 {
                         "n" | "x" => false,
                         _ => unreachable!("Invalid value"),
                     };
-                    board.play(dir, (row, col), val, "".to_string());
+                    board.play(Edge(dir, row, col), val, MoveSource::Import);
                 } else {
                     board.set_solution(l)
                 }
@@ -231,7 +246,7 @@ macro_rules! b_solver {
             }
             .parse()
             .unwrap();
-            fences::solver::solve(b);
+            fences::solver::solve(b).unwrap();
         }
     };
 }
@@ -240,3 +255,47 @@ b_parse! {parse_b, Board}
 b_parse! {parse_b1, Board1}
 b_solver! {solver_b, Board}
 b_solver! {solver_b1, Board1}
+b_parse! {parse_packed, PackedBoard}
+b_solver! {solver_packed, PackedBoard}
+
+fn solved_30x30() -> Board {
+    let mut board: Board = std::fs::read_to_string("puzzle/example-30x30.txt")
+        .unwrap()
+        .parse()
+        .unwrap();
+    solve(&mut board).unwrap();
+    board
+}
+
+/// Lines around every clue, edge by edge.
+#[divan::bench]
+fn clue_lines_b(bencher: divan::Bencher) {
+    let board = solved_30x30();
+    bencher.bench(|| {
+        board
+            .tasks_iter()
+            .filter(|(idx, t)| {
+                t.is_some_and(|t| {
+                    let around = board.cell_edges(*idx);
+                    let lines = around
+                        .iter()
+                        .filter(|&&e| **board.edge(e) == Some(true))
+                        .count();
+                    lines == t as usize
+                })
+            })
+            .count()
+    });
+}
+
+/// Lines around every clue, counted on the bitsets.
+#[divan::bench]
+fn clue_lines_packed(bencher: divan::Bencher) {
+    let board = PackedBoard::from(&solved_30x30());
+    bencher.bench(|| {
+        board
+            .tasks_iter()
+            .filter(|(idx, t)| t.is_some_and(|t| board.cell_lines(*idx) == t as u32))
+            .count()
+    });
+}
@@ -1,6 +1,8 @@
 fn main() {
     divan::main();
 }
+mod causes;
 mod fence_bm;
 mod general;
+mod snapshot;
 mod solver;
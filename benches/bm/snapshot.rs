@@ -0,0 +1,50 @@
+use divan::Bencher;
+use fences::{board::MoveSource, formats::load_puzzle, solver::*, Board, FencesSolver};
+
+/// Guesses played before going back, about what a search tries below a node.
+const GUESSES: usize = 100;
+
+fn board_30x30() -> (Board, Vec<Edge>) {
+    let mut board =
+        load_puzzle(&std::fs::read_to_string("puzzle/example-30x30.txt").unwrap()).unwrap();
+    // Half solved, so there is a move log to copy.
+    let rules = fences::rules::BoardRule::default_rules();
+    solve_with(&mut board, &rules).unwrap();
+    let moves = board.moves().len();
+    board.reset_to(moves / 2).unwrap();
+    let open = board
+        .fences_iter()
+        .filter(|(_, f)| f.is_none())
+        .map(|(e, _)| e)
+        .take(GUESSES)
+        .collect();
+    (board, open)
+}
+
+fn guess(board: &mut Board, open: &[Edge]) {
+    for e in open {
+        board.play(*e, true, MoveSource::Search);
+    }
+}
+
+#[divan::bench]
+fn clone_and_drop(bencher: Bencher) {
+    bencher
+        .with_inputs(board_30x30)
+        .bench_refs(|(board, open)| {
+            let mut b = board.clone();
+            guess(&mut b, open);
+            b
+        });
+}
+
+#[divan::bench]
+fn snapshot_and_restore(bencher: Bencher) {
+    bencher
+        .with_inputs(board_30x30)
+        .bench_refs(|(board, open)| {
+            let snapshot = board.snapshot();
+            guess(board, open);
+            board.restore(snapshot);
+        });
+}
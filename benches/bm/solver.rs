@@ -1,21 +1,24 @@
 use fences::{
-    solver::{block_closed_paths, rules, solve},
+    rules,
+    solver::{block_closed_paths, solve},
     sub_idx, Board, *,
 };
 pub fn solve1(board: &mut Board) {
-    let mut rules = rules::BoardRule::read_rules_from_yaml("assets/rules.yml");
+    let mut rules: Vec<_> = rules::BoardRule::default_rules()
+        .into_iter()
+        .enumerate()
+        .collect();
     loop {
         let mut is_done = true;
-        rules.retain(|r| {
+        rules.retain(|(id, r)| {
             let size = r.task.size();
             let bounds = sub_idx(board.size(), size);
             log::trace!("Trying rule:\n{r}");
             let mut retain = false;
             for idx in (0..=bounds.0)
-                .into_iter()
-                .flat_map(|row| (0..=bounds.1).into_iter().map(move |col| (row, col)))
+                .flat_map(|row| (0..=bounds.1).map(move |col| Cell(row, col)))
             {
-                if let Some(x) = r.apply_at(board, idx) {
+                if let Some(x) = r.apply_at(board, idx, *id).unwrap() {
                     retain |= x;
                     is_done &= x;
                 }
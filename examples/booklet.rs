@@ -0,0 +1,33 @@
+use anyhow::Result;
+use fences::{
+    formats::load_puzzle,
+    pdf::{write_booklet, BookletOptions, BookletPuzzle},
+    rules::BoardRule,
+};
+use std::env;
+
+fn main() -> Result<()> {
+    let mut args = env::args().skip(1);
+    let out = args.next().unwrap_or("booklet.pdf".to_string());
+    let puzzles = args
+        .map(|file| {
+            Ok(BookletPuzzle {
+                board: load_puzzle(&std::fs::read_to_string(&file)?)?,
+                title: file,
+                difficulty: None,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let rules = BoardRule::default_rules();
+    write_booklet(
+        &out,
+        &puzzles,
+        &rules,
+        &BookletOptions {
+            per_page: 2,
+            ..Default::default()
+        },
+    )?;
+    println!("Wrote {} puzzles to `{out}`", puzzles.len());
+    Ok(())
+}
@@ -0,0 +1,16 @@
+use anyhow::Result;
+use std::env;
+
+fn main() -> Result<()> {
+    let out = env::args()
+        .nth(1)
+        .unwrap_or("rule_catalogue.md".to_string());
+    let rotations = env::args().any(|a| a == "--rotations");
+    fences::catalogue::write_rule_catalogue(
+        &fences::rules::BoardRule::default_base_rules(),
+        &out,
+        rotations,
+    )?;
+    println!("Wrote `{out}`");
+    Ok(())
+}
@@ -1,23 +1,17 @@
 use anyhow::Result;
-use fences::game;
+use fences::{game, rules::BoardRule, save::save_path, SaveGame};
 use std::env;
 
 fn main() -> Result<()> {
     simple_logger::init_with_level(log::Level::Info).unwrap();
-    let file = env::args().last().unwrap();
+    let file = env::args().next_back().unwrap();
     println!("{file}");
-    let sol_file = if file.ends_with(".sol.txt") {
-        file.clone()
+    let sol_file = save_path(&file);
+    let save = if std::path::Path::new(&sol_file).exists() {
+        SaveGame::load(&sol_file)?
     } else {
-        file.clone().replace(".txt", ".sol.txt")
+        SaveGame::load(&file)?
     };
-    let mut board = std::fs::read_to_string(&if std::path::Path::new(&sol_file).exists() {
-        sol_file.clone()
-    } else {
-        file
-    })?
-    .parse()
-    .unwrap();
 
-    game(&mut board, &sol_file)
+    game(save, &sol_file, &BoardRule::default_rules())
 }
@@ -0,0 +1,304 @@
+use crate::{
+    board::MoveSource, formats::load_puzzle, rules::BoardRule, save::now, solver::solve_with,
+    BoardGeom, FencesSolver,
+};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Solved,
+    Partial,
+    Contradiction,
+    /// The puzzle could not be loaded.
+    Error,
+}
+
+impl std::fmt::Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", format!("{self:?}").to_lowercase())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchEntry {
+    pub file: String,
+    pub rows: usize,
+    pub cols: usize,
+    pub outcome: Outcome,
+    /// Edges decided by the solver, including the ones given.
+    pub decided: usize,
+    pub total: usize,
+    pub millis: f64,
+    /// Deductions per rule, keyed `rule N` by position in the rule file or
+    /// by [`MoveSource`] for deductions not made by a rule.
+    pub rules: BTreeMap<String, usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl BatchEntry {
+    pub fn percent(&self) -> f64 {
+        if self.total == 0 {
+            0.
+        } else {
+            100. * self.decided as f64 / self.total as f64
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchReport {
+    /// Unix time the report was made.
+    pub created: u64,
+    pub rules: String,
+    pub puzzles: Vec<BatchEntry>,
+}
+
+/// Puzzle files under `path`, sorted, skipping hidden files and `.sol.txt`
+/// saves. A file is returned as is, and so is a directory below `path` that
+/// can't be read, to show up as an error in the report.
+pub fn puzzle_files(path: &Path) -> Result<Vec<PathBuf>> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut ret = vec![];
+    let dir =
+        std::fs::read_dir(path).with_context(|| format!("Couldn't read `{}`", path.display()))?;
+    for entry in dir {
+        let Ok(p) = entry.map(|e| e.path()) else {
+            continue;
+        };
+        let name = p
+            .file_name()
+            .map_or(String::new(), |n| n.to_string_lossy().into());
+        if name.starts_with('.') || name.ends_with(".sol.txt") {
+            continue;
+        }
+        if p.is_dir() {
+            match puzzle_files(&p) {
+                Ok(files) => ret.extend(files),
+                Err(_) => ret.push(p),
+            }
+        } else {
+            ret.push(p);
+        }
+    }
+    ret.sort();
+    Ok(ret)
+}
+
+fn solve_entry(file: &Path, rules: &[BoardRule], base: &[usize]) -> BatchEntry {
+    let mut entry = BatchEntry {
+        file: file.display().to_string(),
+        rows: 0,
+        cols: 0,
+        outcome: Outcome::Error,
+        decided: 0,
+        total: 0,
+        millis: 0.,
+        rules: BTreeMap::new(),
+        error: None,
+    };
+    let mut board = match std::fs::read_to_string(file)
+        .map_err(anyhow::Error::from)
+        .and_then(|s| load_puzzle(&s))
+    {
+        Ok(board) => board,
+        Err(e) => {
+            entry.error = Some(format!("{e:#}"));
+            return entry;
+        }
+    };
+    (entry.rows, entry.cols) = board.size();
+    let before = board.moves().len();
+    let start = Instant::now();
+    let solved = solve_with(&mut board, rules);
+    entry.millis = start.elapsed().as_secs_f64() * 1000.;
+    for m in &board.moves()[before..] {
+        let key = match m.source {
+            MoveSource::Rule { id, .. } => format!("rule {}", base[id]),
+            s => s.to_string(),
+        };
+        *entry.rules.entry(key).or_default() += 1;
+    }
+    entry.total = board.fences_iter().count();
+    entry.decided = board.fences_iter().filter(|(_, f)| f.is_some()).count();
+    entry.outcome = match board.result() {
+        _ if solved.is_err() => Outcome::Contradiction,
+        Some(true) => Outcome::Solved,
+        Some(false) => Outcome::Contradiction,
+        None => Outcome::Partial,
+    };
+    entry
+}
+
+/// Runs the rules of `rules_file`, the built-in ones when `None`, over every
+/// puzzle in `paths`.
+pub fn batch_solve(paths: &[PathBuf], rules_file: Option<&str>) -> Result<BatchReport> {
+    let base = match rules_file {
+        Some(file) => BoardRule::read_base_rules_from_yaml(file)?,
+        None => BoardRule::default_base_rules(),
+    };
+    let rotations: Vec<_> = base.iter().map(|r| r.get_rotations()).collect();
+    // The base rule of each rotation.
+    let ids: Vec<_> = rotations
+        .iter()
+        .enumerate()
+        .flat_map(|(i, r)| std::iter::repeat_n(i, r.len()))
+        .collect();
+    let rules: Vec<_> = rotations.into_iter().flatten().collect();
+    let mut files = vec![];
+    for p in paths {
+        files.extend(puzzle_files(p)?);
+    }
+    Ok(BatchReport {
+        created: now(),
+        rules: rules_file.unwrap_or("built-in").to_string(),
+        puzzles: files.iter().map(|f| solve_entry(f, &rules, &ids)).collect(),
+    })
+}
+
+fn rules_summary(entry: &BatchEntry) -> String {
+    let mut rules: Vec<_> = entry
+        .rules
+        .iter()
+        .map(|(k, v)| (k.strip_prefix("rule ").and_then(|i| i.parse().ok()), k, v))
+        .collect();
+    // Numbered rules in file order, then the other deductions.
+    rules.sort_by_key(|&(i, k, _)| (i.unwrap_or(usize::MAX), k));
+    rules
+        .iter()
+        .map(|(i, k, v)| match i {
+            Some(i) => format!("{i}:{v}"),
+            None => format!("{k}:{v}"),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl BatchReport {
+    /// A table with one line per puzzle and totals at the end.
+    pub fn table(&self) -> String {
+        let width = self
+            .puzzles
+            .iter()
+            .map(|e| e.file.len())
+            .max()
+            .unwrap_or(0)
+            .max(6);
+        let mut s = format!(
+            "{:width$}  {:>7}  {:13}  {:>6}  {:>9}  rules\n",
+            "puzzle", "size", "outcome", "edges", "time"
+        );
+        for e in &self.puzzles {
+            let outcome = match e.outcome {
+                Outcome::Partial => format!("partial {:.0}%", e.percent()),
+                x => x.to_string(),
+            };
+            writeln!(
+                s,
+                "{:width$}  {:>7}  {outcome:13}  {:>6}  {:>7.1}ms  {}",
+                e.file,
+                format!("{}x{}", e.rows, e.cols),
+                e.decided,
+                e.millis,
+                e.error.clone().unwrap_or_else(|| rules_summary(e)),
+            )
+            .unwrap();
+        }
+        let count = |o: Outcome| self.puzzles.iter().filter(|e| e.outcome == o).count();
+        writeln!(
+            s,
+            "{} puzzles: {} solved, {} partial, {} contradiction, {} errors in {:.1}ms",
+            self.puzzles.len(),
+            count(Outcome::Solved),
+            count(Outcome::Partial),
+            count(Outcome::Contradiction),
+            count(Outcome::Error),
+            self.puzzles.iter().map(|e| e.millis).sum::<f64>()
+        )
+        .unwrap();
+        s
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)? + "\n")
+    }
+
+    /// One row per puzzle, rules as space separated `rule:count` pairs.
+    pub fn to_csv(&self) -> String {
+        let quote = |s: &str| {
+            if s.contains([',', '"', '\n']) {
+                format!("\"{}\"", s.replace('"', "\"\""))
+            } else {
+                s.to_string()
+            }
+        };
+        let mut s = String::from("file,rows,cols,outcome,decided,total,percent,millis,rules\n");
+        for e in &self.puzzles {
+            writeln!(
+                s,
+                "{},{},{},{},{},{},{:.1},{:.3},{}",
+                quote(&e.file),
+                e.rows,
+                e.cols,
+                e.outcome,
+                e.decided,
+                e.total,
+                e.percent(),
+                e.millis,
+                quote(&rules_summary(e))
+            )
+            .unwrap();
+        }
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn check_batch_report() {
+        let report = batch_solve(&["puzzle/example-5x5.txt".into()], None).unwrap();
+        let e = &report.puzzles[0];
+        assert_eq!(e.outcome, Outcome::Solved);
+        assert_eq!((e.decided, e.total), (60, 60));
+        assert!(e.rules.keys().any(|k| k.starts_with("rule ")));
+        assert!(report.table().contains("1 puzzles: 1 solved"));
+        assert_eq!(report.to_csv().lines().count(), 2);
+        assert!(report
+            .to_json()
+            .unwrap()
+            .contains("\"outcome\": \"solved\""));
+    }
+    #[test]
+    fn check_batch_errors() {
+        let dir = std::env::temp_dir().join(format!("fences-batch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::copy("puzzle/example-5x5.txt", dir.join("a.txt")).unwrap();
+        std::fs::write(dir.join("README.md"), "# Puzzles\n\nSome of them.\n").unwrap();
+        std::fs::write(dir.join("b.txt"), [0xff, 0xfe, 0]).unwrap();
+        std::fs::write(dir.join("c.txt"), "3#12\n").unwrap();
+        let report = batch_solve(std::slice::from_ref(&dir), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+        let outcomes: Vec<_> = report.unwrap().puzzles.iter().map(|e| e.outcome).collect();
+        assert_eq!(
+            outcomes,
+            [
+                Outcome::Error,
+                Outcome::Solved,
+                Outcome::Error,
+                Outcome::Error
+            ]
+        );
+    }
+}
@@ -16,19 +16,65 @@ const BOX_VERTICAL_HORIZONTAL: char = '┼';
 const DOT: char = '∙';
 const CROSS: char = '×';
 
-#[derive(Debug, Clone)]
+/// What decided an edge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveSource {
+    Player,
+    /// The rule at `id` of the rules given to the solver, matched with its
+    /// top left corner on cell `at`.
+    Rule {
+        id: usize,
+        at: Cell,
+    },
+    /// Crossed so a path doesn't close into a loop too early.
+    Closure,
+    /// Crossed around a 0 or a dot that already has two lines.
+    Trivial,
+    /// A guess of the search, or what follows from one.
+    Search,
+    /// Read along with the puzzle.
+    Import,
+}
+
+impl fmt::Display for MoveSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveSource::Player => write!(f, "player"),
+            MoveSource::Rule { id, at } => write!(f, "rule {id} at {at}"),
+            MoveSource::Closure => write!(f, "closed loop"),
+            MoveSource::Trivial => write!(f, "trivial"),
+            MoveSource::Search => write!(f, "search"),
+            MoveSource::Import => write!(f, "import"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Move {
-    pub direction: usize,
-    pub idx: (usize, usize),
+    pub edge: Edge,
     pub value: bool,
-    pub name: String,
+    pub source: MoveSource,
+}
+
+impl Move {
+    /// What made the move, with the rule written out for rule moves.
+    /// `rules` are the ones the solver was given.
+    pub fn cause(&self, rules: &[BoardRule]) -> String {
+        match self.source {
+            MoveSource::Rule { id, .. } if id < rules.len() => {
+                format!("{}\n{}", self.source, rules[id])
+            }
+            s => s.to_string(),
+        }
+    }
 }
 
-use crate::Fence;
+use crate::{Fence, Fences};
 
 use crate::{
-    geom::BoardGeom,
-    solver::{FencesSolver, Idx},
+    geom::{BoardGeom, Cell, Direction, Dot, Edge},
+    rules::BoardRule,
+    solver::{Changed, Conflict, FencesSolver, Snapshot},
 };
 #[derive(Debug, Clone)]
 pub struct Board {
@@ -37,30 +83,27 @@ pub struct Board {
     moves: Vec<Move>,
 }
 
-pub type Fences = [Grid<Fence>; 2];
 pub type Tasks = Grid<Option<u8>>;
 pub type Task = Option<u8>;
 
 pub fn print_board(task: &Tasks, fences: &Fences, color: bool) -> String {
     let paths = if color {
-        let mut paths = get_paths(fences);
-        paths.sort_by(|a, b| (b.len(), b[0]).cmp(&(a.len(), a[0])));
-        paths
+        sorted_paths(fences)
     } else {
         vec![]
     };
-    let get_edge = |dir: usize, row, col| -> String {
-        if let Some(edge) = fences[dir][(row, col)].0 {
+    let get_edge = |at: Edge| -> String {
+        if let Some(edge) = fences[at].0 {
             if edge {
                 let e = format!(
                     "{}",
-                    if dir == 1 {
+                    if at.0 == Direction::Vertical {
                         BOX_VERTICAL
                     } else {
                         BOX_HORIZONTAL
                     }
                 );
-                if let Some(color) = paths.iter().position(|r| r.contains(&(dir, row, col))) {
+                if let Some(color) = paths.iter().position(|r| r.contains(&at)) {
                     format!(
                         "{}",
                         e.color(["white", "green", "yellow", "cyan", "purple", "red"][color % 6])
@@ -82,20 +125,11 @@ pub fn print_board(task: &Tasks, fences: &Fences, color: bool) -> String {
 
     let (rows, cols) = task.size();
     let get_dot_char = |row, col| {
-        let mut n = [Fence::default(); 4];
-        if col < cols {
-            n[0] = fences[0][(row, col)];
-        }
-        if row < rows {
-            n[1] = fences[1][(row, col)]
-        }
-        if col > 0 {
-            n[2] = fences[0][(row, col - 1)]
-        }
-        if row > 0 {
-            n[3] = fences[1][(row - 1, col)]
-        }
-        let n = n.map(|v| v.is_some_and(|x| x));
+        // Right, down, left, up.
+        let n = Dot(row, col).edges().map(|e| {
+            e.and_then(|e| fences.get(e))
+                .is_some_and(|v| v.is_some_and(|x| x))
+        });
         if n == [true; 4] {
             BOX_VERTICAL_HORIZONTAL
         } else if n == [true, true, false, false] {
@@ -126,20 +160,24 @@ pub fn print_board(task: &Tasks, fences: &Fences, color: bool) -> String {
     for row in 0..rows {
         for col in 0..cols {
             f += &format!("{}", get_dot_char(row, col));
-            f += &format!("{}", get_edge(0, row, col));
+            f += &get_edge(Edge::horizontal(row, col)).to_string();
         }
         f += &format!("{}\n", get_dot_char(row, cols));
         for col in 0..cols {
             f += &format!(
                 "{}{}",
-                get_edge(1, row, col),
+                get_edge(Edge::vertical(row, col)),
                 task[(row, col)].map_or(" ".to_string(), |x| x.to_string())
             );
         }
-        f += &format!("{}\n", get_edge(1, row, cols),);
+        f += &format!("{}\n", get_edge(Edge::vertical(row, cols)),);
     }
     for col in 0..cols {
-        f += &format!("{}{}", get_dot_char(rows, col), get_edge(0, rows, col),);
+        f += &format!(
+            "{}{}",
+            get_dot_char(rows, col),
+            get_edge(Edge::horizontal(rows, col)),
+        );
     }
     f += &format!("{}", get_dot_char(rows, cols));
     f
@@ -147,31 +185,24 @@ pub fn print_board(task: &Tasks, fences: &Fences, color: bool) -> String {
 
 impl fmt::Display for Board {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "      {}\n",
-            (0..self.tasks.cols()).fold("".to_string(), |acc, x| format!("{acc}{x:2}"))
-        )?;
-        for (i, x) in print_board(&self.tasks, &self.fences, true)
-            .lines()
-            .enumerate()
-        {
-            write!(
-                f,
-                "{}",
-                if i % 2 == 1 {
-                    format!("{:3} ║ {x} ║{0:3}\n", i / 2)
-                } else {
-                    format!("    ║ {x} ║\n")
-                }
-            )?;
+        let board = print_board(&self.tasks, &self.fences, true);
+        write_ruled(f, &board, self.tasks.cols())
+    }
+}
+
+/// Writes a drawing from [`print_board`] framed by the row and column
+/// numbers.
+pub(crate) fn write_ruled(f: &mut fmt::Formatter<'_>, board: &str, cols: usize) -> fmt::Result {
+    let ruler = (0..cols).fold("".to_string(), |acc, x| format!("{acc}{x:2}"));
+    writeln!(f, "      {ruler}")?;
+    for (i, x) in board.lines().enumerate() {
+        if i % 2 == 1 {
+            writeln!(f, "{:3} ║ {x} ║{0:3}", i / 2)?;
+        } else {
+            writeln!(f, "    ║ {x} ║")?;
         }
-        write!(
-            f,
-            "      {}\n",
-            (0..self.tasks.cols()).fold("".to_string(), |acc, x| format!("{acc}{x:2}"))
-        )
     }
+    writeln!(f, "      {ruler}")
 }
 
 impl BoardGeom for Board {
@@ -187,196 +218,323 @@ impl FencesSolver for Board {
     fn set_solution(&mut self, solution: &str) {
         self.fences
             .iter_mut()
-            .flat_map(|f| f.iter_mut())
+            .map(|(_, f)| f)
             .zip(solution.chars())
             .for_each(|(f, v)| *f = v.try_into().unwrap());
         log::info!("set_solution\n{self}");
     }
-    fn play(&mut self, direction: usize, idx: (usize, usize), value: bool, name: String) {
-        if let Some(curr) = self.fences[direction][idx].0 {
+    fn try_play(
+        &mut self,
+        edge: Edge,
+        value: bool,
+        source: MoveSource,
+    ) -> Result<Changed, Conflict> {
+        if let Some(curr) = self.fences[edge].0 {
             if curr == value {
-                log::trace!("Trying to overwrite an existing fence at [{direction}][{idx:?}]");
-                return;
+                log::trace!("Trying to overwrite an existing fence at {edge}");
+                return Ok(Changed::Unchanged);
             }
-            log::warn!("Overwriting an existing fence {curr} with {value} by {name}")
+            return Err(Conflict {
+                edge,
+                value,
+                source,
+                by: self
+                    .moves
+                    .iter()
+                    .rfind(|m| m.edge == edge)
+                    .cloned()
+                    .map(Box::new),
+            });
         }
-        *self.fences[direction][idx] = Some(value);
+        *self.fences[edge] = Some(value);
         let m = Move {
-            direction,
-            idx,
+            edge,
             value,
-            name,
+            source,
         };
-        log::trace!("{:?}\n{}{self}", (m.direction, m.idx, m.value), m.name);
+        log::trace!("{} {} by {}", m.edge, m.value, m.source);
         self.moves.push(m);
+        Ok(Changed::Set)
     }
-    fn tasks_iter(&self) -> impl Iterator<Item = (Idx, &Task)> {
-        self.tasks.indexed_iter()
+    fn snapshot(&self) -> Snapshot {
+        Snapshot(self.moves.len())
     }
-    fn task(&self, idx: Idx) -> &Task {
-        &self.tasks[idx]
+    fn restore(&mut self, snapshot: Snapshot) {
+        debug_assert!(snapshot.0 <= self.moves.len(), "Restoring a lost snapshot");
+        while self.moves.len() > snapshot.0 {
+            let m = self.moves.pop().unwrap();
+            self.fences[m.edge].0 = None;
+        }
     }
-    fn edge(&self, dir: usize, idx: Idx) -> &Fence {
-        &self.fences[dir][idx]
+    fn tasks_iter(&self) -> impl Iterator<Item = (Cell, &Task)> {
+        self.tasks.indexed_iter().map(|(idx, t)| (idx.into(), t))
     }
-    fn fences_iter(&self) -> impl Iterator<Item = (crate::solver::Edge, &Fence)> {
-        (0usize..2).into_iter().flat_map(|dir| {
-            self.fences[dir]
-                .indexed_iter()
-                .map(move |((row, col), val)| ((dir, row, col), val))
-        })
+    fn task(&self, cell: Cell) -> &Task {
+        &self.tasks[cell.into()]
+    }
+    fn edge(&self, e: Edge) -> &Fence {
+        &self.fences[e]
+    }
+    fn fences_iter(&self) -> impl Iterator<Item = (Edge, &Fence)> {
+        self.fences.iter()
     }
 }
 
 impl Board {
+    /// A board with the given clues and no fences decided.
+    pub fn new(tasks: Tasks) -> Self {
+        Board {
+            fences: Fences::new(tasks.rows(), tasks.cols()),
+            tasks,
+            moves: vec![],
+        }
+    }
     pub fn solution(&self) -> String {
         self.fences
             .iter()
-            .flat_map(|f| {
-                f.iter().map(|e| match e.0 {
-                    Some(true) => 'y',
-                    _ => 'n',
-                })
+            .map(|(_, e)| match e.0 {
+                Some(true) => 'y',
+                _ => 'n',
             })
             .collect()
     }
+    /// Moves that disagree with `solution`, as given by [`Board::solution`],
+    /// with their position in [`Board::moves`].
+    pub fn mistakes<'a>(&'a self, solution: &'a str) -> impl Iterator<Item = (usize, &'a Move)> {
+        let solution = solution.as_bytes();
+        self.moves.iter().enumerate().filter(move |(_, m)| {
+            let id = self.fences.id(m.edge);
+            solution.get(id.0).is_some_and(|&x| (x == b'y') != m.value)
+        })
+    }
+    /// The clues in `cols#clues` notation.
+    pub fn puzzle(&self) -> String {
+        format!(
+            "{}#{}",
+            self.cols(),
+            self.tasks
+                .iter()
+                .map(|x| x.map_or(' ', |x| (b'0' + x) as char))
+                .collect::<String>()
+        )
+    }
+    pub fn tasks(&self) -> &Tasks {
+        &self.tasks
+    }
+    pub fn fences(&self) -> &Fences {
+        &self.fences
+    }
     pub fn moves(&self) -> &Vec<Move> {
         &self.moves
     }
+    /// Player moves with their position in [`Board::moves`].
+    pub fn player_moves(&self) -> impl Iterator<Item = (usize, &Move)> {
+        self.moves
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| m.source == MoveSource::Player)
+    }
+    /// The moves that followed from player move `n`, counting from 0.
+    pub fn deductions(&self, n: usize) -> &[Move] {
+        let mut players = self.player_moves().map(|(i, _)| i).skip(n);
+        match players.next() {
+            Some(start) => &self.moves[start + 1..players.next().unwrap_or(self.moves.len())],
+            None => &[],
+        }
+    }
+    /// Takes back the last player move and what followed from it, returning
+    /// the moves removed.
+    pub fn undo_player_move(&mut self) -> Option<Vec<Move>> {
+        let (start, _) = self.player_moves().last()?;
+        let removed = self.moves[start..].to_vec();
+        self.reset_to(start).ok()?;
+        Some(removed)
+    }
+    /// Erases an edge along with the deductions made after it, as they may
+    /// have followed from it, returning the moves removed. Later player and
+    /// imported moves stay, so snapshots taken after the edge was decided
+    /// are lost.
+    pub fn clear(&mut self, edge: Edge) -> Option<Vec<Move>> {
+        let k = self.moves.iter().rposition(|m| m.edge == edge)?;
+        let later = self.moves.split_off(k);
+        later.iter().for_each(|m| self.fences[m.edge].0 = None);
+        let (kept, mut removed): (Vec<_>, Vec<_>) = later[1..]
+            .iter()
+            .cloned()
+            .partition(|m| matches!(m.source, MoveSource::Player | MoveSource::Import));
+        for m in kept {
+            self.fences[m.edge].0 = Some(m.value);
+            self.moves.push(m);
+        }
+        removed.insert(0, later[0].clone());
+        Some(removed)
+    }
     pub fn reset_to(&mut self, to: usize) -> anyhow::Result<()> {
         if to > self.moves.len() {
             anyhow::bail!("Invalid reset entry")
         }
-        while self.moves.len() > to {
-            let e = self.moves.pop().unwrap();
-            self.fences[e.direction][e.idx].0 = None;
-        }
+        self.restore(Snapshot(to));
         anyhow::Ok(())
     }
-    // #[inline]
-    // pub fn get_dot_fences(&self, idx: (usize, usize)) -> Vec<(usize, usize, usize)> {}
-    pub fn result(&self) -> Option<bool> {
+    /// Everything on the board that breaks a rule of the game. Empty when
+    /// the board can still be completed as far as the fences alone show.
+    pub fn validate(&self) -> Vec<Violation> {
         let (rows, cols) = self.size();
-        #[derive(Debug)]
-        struct Count {
-            xs: usize,
-            dashes: usize,
-        }
-        impl Count {
-            fn incr(&mut self, value: Option<bool>) {
-                match value {
-                    Some(true) => self.dashes += 1,
-                    Some(false) => self.xs += 1,
-                    _ => (),
-                }
-            }
-        }
-
-        #[cfg(test)]
-        print!("{self}");
-        // Check for dots and tasks within the board
+        let value = |e: &Edge| self.fences[*e].0;
+        let mut violations = vec![];
         for row in 0..=rows {
             for col in 0..=cols {
-                let node = &mut Count { xs: 0, dashes: 0 };
-                if col < cols {
-                    node.incr(self.fences[0][(row, col)].0);
-                }
-                if row < rows {
-                    node.incr(self.fences[1][(row, col)].0);
-                }
-                if col > 0 {
-                    node.incr(self.fences[0][(row, col - 1)].0);
-                }
-                if row > 0 {
-                    node.incr(self.fences[1][(row - 1, col)].0);
-                }
-                #[cfg(test)]
-                println!("Node at {:?} -> {node:?}", (row, col));
-                if node.dashes > 2 || node.dashes == 1 && node.xs == 3 {
-                    return Some(false);
+                let edges = self.dot_edges(Dot(row, col));
+                let lines: Vec<_> = edges
+                    .iter()
+                    .copied()
+                    .filter(|e| value(e) == Some(true))
+                    .collect();
+                if lines.len() > 2 {
+                    violations.push(Violation::Branch {
+                        dot: Dot(row, col),
+                        edges: lines,
+                    });
+                } else if lines.len() == 1 && edges.iter().all(|e| value(e).is_some()) {
+                    violations.push(Violation::DeadEnd {
+                        dot: Dot(row, col),
+                        edges,
+                    });
                 }
             }
         }
-        for row in 0..rows {
-            for col in 0..cols {
-                let task = &mut Count { xs: 0, dashes: 0 };
-                task.incr(self.fences[0][(row, col)].0);
-                task.incr(self.fences[1][(row, col)].0);
-                task.incr(self.fences[0][(row + 1, col)].0);
-                task.incr(self.fences[1][(row, col + 1)].0);
-                #[cfg(test)]
-                println!("Task at {:?} -> {task:?}", (row, col));
-                if self.tasks[(row, col)]
-                    .is_some_and(|x| task.dashes as u8 > x || task.xs as u8 > 4u8 - x)
-                {
-                    return Some(false);
-                }
+        let paths = get_paths(&self.fences);
+        let loops = paths
+            .iter()
+            .filter(|p| p.len() > 2 && p[0].is_linked(*p.last().unwrap()))
+            .count();
+        for (cell, task) in self.tasks_iter() {
+            let Some(count) = task.map(usize::from) else {
+                continue;
+            };
+            let edges = self.cell_edges(cell);
+            let lines = edges.iter().filter(|e| value(e) == Some(true)).count();
+            let crosses = edges.iter().filter(|e| value(e) == Some(false)).count();
+            if lines > count {
+                violations.push(Violation::Over {
+                    cell,
+                    edges: edges
+                        .into_iter()
+                        .filter(|e| value(e) == Some(true))
+                        .collect(),
+                });
+            } else if crosses > 4 - count || lines < count && loops > 0 {
+                // A closed loop leaves no way to add the missing lines.
+                violations.push(Violation::Under {
+                    cell,
+                    edges: edges
+                        .into_iter()
+                        .filter(|e| value(e) != Some(true))
+                        .collect(),
+                });
             }
         }
-
-        if self.tasks.indexed_iter().all(|((row, col), val)| {
-            if val.is_none() {
-                return true;
+        if paths.len() > 1 {
+            for p in paths.iter() {
+                if p.len() > 2 && p[0].is_linked(*p.last().unwrap()) {
+                    violations.push(Violation::Loop { edges: p.clone() });
+                }
             }
-            let task = &mut Count { xs: 0, dashes: 0 };
-            task.incr(self.fences[0][(row, col)].0);
-            task.incr(self.fences[1][(row, col)].0);
-            task.incr(self.fences[0][(row + 1, col)].0);
-            task.incr(self.fences[1][(row, col + 1)].0);
-            #[cfg(test)]
-            println!("Task at {:?} -> {task:?}", (row, col));
-            self.tasks[(row, col)].is_some_and(|x| task.dashes as u8 == x)
-        }) && has_one_path_and_is_circular(&self.fences)
-        {
-            return Some(true);
-        } else {
-            let paths = get_paths(&self.fences);
-            if paths
-                .iter()
-                .any(|p| p.len() > 2 && are_linked(&p[0], p.last().unwrap()))
-            {
-                return Some(false);
+            if self.fences.iter().all(|(_, e)| e.is_some()) {
+                violations.push(Violation::Disconnected { paths });
             }
         }
-        None
+        violations
+    }
+    pub fn result(&self) -> Option<bool> {
+        #[cfg(test)]
+        print!("{self}");
+        if !self.validate().is_empty() {
+            return Some(false);
+        }
+        let satisfied = self.tasks_iter().all(|(cell, t)| {
+            t.is_none_or(|x| {
+                self.cell_edges(cell)
+                    .iter()
+                    .filter(|&&e| self.fences[e].0 == Some(true))
+                    .count()
+                    == x as usize
+            })
+        });
+        if satisfied && has_one_path_and_is_circular(&self.fences) {
+            Some(true)
+        } else {
+            None
+        }
     }
 }
 
-use crate::solver::Edge;
-pub fn are_linked(l: &Edge, r: &Edge) -> bool {
-    if r.0 != l.0 {
-        let (r, l) = if r.0 == 0 { (r, l) } else { (l, r) };
-        [(0, 0), (0, -1), (1, -1), (1, 0)]
-            .contains(&(r.1 as isize - l.1 as isize, r.2 as isize - l.2 as isize))
-    } else {
-        let diff = (r.1.abs_diff(l.1), r.2.abs_diff(l.2));
-        if r.0 == 0 {
-            diff == (0, 1)
-        } else {
-            diff == (1, 0)
+/// A way the board breaks the rules of the game, see [`Board::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// A dot with more than two lines.
+    Branch { dot: Dot, edges: Vec<Edge> },
+    /// A dot with a single line and every other edge crossed.
+    DeadEnd { dot: Dot, edges: Vec<Edge> },
+    /// A clue with more lines around it than its count, and those lines.
+    Over { cell: Cell, edges: Vec<Edge> },
+    /// A clue that can't get its count any more, and the edges around it
+    /// that are not lines.
+    Under { cell: Cell, edges: Vec<Edge> },
+    /// A closed loop while other lines remain.
+    Loop { edges: Vec<Edge> },
+    /// Every edge is decided but the lines make more than one path.
+    Disconnected { paths: Vec<Vec<Edge>> },
+}
+
+impl Violation {
+    /// The edges to highlight.
+    pub fn edges(&self) -> Vec<Edge> {
+        match self {
+            Violation::Branch { edges, .. }
+            | Violation::DeadEnd { edges, .. }
+            | Violation::Over { edges, .. }
+            | Violation::Under { edges, .. }
+            | Violation::Loop { edges } => edges.clone(),
+            Violation::Disconnected { paths } => paths.concat(),
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::Branch { dot, edges } => {
+                write!(f, "dot {dot} has {} lines", edges.len())
+            }
+            Violation::DeadEnd { dot, .. } => write!(f, "dot {dot} is a dead end"),
+            Violation::Over { cell, .. } => write!(f, "cell {cell} has too many lines"),
+            Violation::Under { cell, .. } => write!(f, "cell {cell} can't get enough lines"),
+            Violation::Loop { edges } => {
+                write!(f, "the loop through edge {} is closed too early", edges[0])
+            }
+            Violation::Disconnected { paths } => {
+                write!(f, "the lines make {} separate paths", paths.len())
+            }
         }
     }
-    // println!("are_linked(r: {r:?}, l: {l:?}) -> {a}");
 }
 
 fn has_one_path_and_is_circular(fences: &Fences) -> bool {
     let paths = get_paths(fences);
-    paths.len() == 1 && are_linked(&paths[0][0], paths[0].last().unwrap())
+    paths.len() == 1 && paths[0][0].is_linked(*paths[0].last().unwrap())
 }
-pub fn get_paths(fences: &Fences) -> Vec<Vec<(usize, usize, usize)>> {
-    let mut dashes: Vec<_> = (0usize..2)
-        .into_iter()
-        .flat_map(|dir| {
-            fences[dir]
-                .indexed_iter()
-                .filter_map(move |((row, col), val)| {
-                    if val.is_some_and(|x| x) {
-                        Some((dir, row, col))
-                    } else {
-                        None
-                    }
-                })
-        })
+/// Paths ordered longest first, the order used to pick a colour for each path.
+pub fn sorted_paths(fences: &Fences) -> Vec<Vec<Edge>> {
+    let mut paths = get_paths(fences);
+    paths.sort_by(|a, b| (b.len(), b[0]).cmp(&(a.len(), a[0])));
+    paths
+}
+pub fn get_paths(fences: &Fences) -> Vec<Vec<Edge>> {
+    let mut dashes: Vec<_> = fences
+        .iter()
+        .filter(|(_, val)| val.is_some_and(|x| x))
+        .map(|(e, _)| e)
         .collect();
     #[cfg(test)]
     println!("Dashes:{dashes:?}",);
@@ -389,14 +547,14 @@ pub fn get_paths(fences: &Fences) -> Vec<Vec<(usize, usize, usize)>> {
         let mut row_changed = false;
         if let Some(index) = dashes
             .iter()
-            .position(|l| are_linked(l, row.front().unwrap()))
+            .position(|l| l.is_linked(*row.front().unwrap()))
         {
             row.push_front(dashes.swap_remove(index));
             row_changed = true;
         }
         if let Some(index) = dashes
             .iter()
-            .position(|l| are_linked(l, row.back().unwrap()))
+            .position(|l| l.is_linked(*row.back().unwrap()))
         {
             row.push_back(dashes.swap_remove(index));
             row_changed = true;
@@ -420,72 +578,187 @@ pub fn get_paths(fences: &Fences) -> Vec<Vec<(usize, usize, usize)>> {
     ret
 }
 
+fn strip_ansi(s: &str) -> String {
+    let mut ret = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            ret.push(c);
+        }
+    }
+    ret
+}
+
+/// Whether `s` looks like the output of [`print_board`] or `Display for Board`.
+pub fn is_drawing(s: &str) -> bool {
+    s.contains(|c| {
+        matches!(
+            c,
+            DOT | BOX_HORIZONTAL
+                | BOX_VERTICAL
+                | BOX_DOWN_RIGHT
+                | BOX_DOWN_LEFT
+                | BOX_UP_RIGHT
+                | BOX_UP_LEFT
+                | BOX_VERTICAL_RIGHT
+                | BOX_VERTICAL_LEFT
+                | BOX_HORIZONTAL_DOWN
+                | BOX_HORIZONTAL_UP
+                | BOX_VERTICAL_HORIZONTAL
+                | '║'
+        )
+    })
+}
+
+/// Reads back a board drawn by [`print_board`], with or without the rulers
+/// added by `Display for Board` and the terminal colours.
+pub fn parse_drawing(s: &str) -> Result<Board, &'static str> {
+    let s = strip_ansi(s);
+    let lines: Vec<Vec<char>> = if s.contains('║') {
+        s.lines()
+            .filter(|l| l.matches('║').count() == 2)
+            .map(|l| {
+                let l = &l[l.find('║').unwrap() + '║'.len_utf8()..l.rfind('║').unwrap()];
+                let l = l.strip_prefix(' ').unwrap_or(l);
+                l.strip_suffix(' ').unwrap_or(l).chars().collect()
+            })
+            .collect()
+    } else {
+        let lines: Vec<_> = s.lines().map(|l| l.trim_end_matches('\r')).collect();
+        let first = lines.iter().position(|l| !l.trim().is_empty());
+        let last = lines.iter().rposition(|l| !l.trim().is_empty());
+        match (first, last) {
+            (Some(first), Some(last)) => lines[first..=last]
+                .iter()
+                .map(|l| l.chars().collect())
+                .collect(),
+            _ => vec![],
+        }
+    };
+    if lines.len() < 3 || lines.len().is_multiple_of(2) {
+        return Err("A drawing needs an odd number of lines, alternating dots and cells");
+    }
+    let width = lines.iter().map(|l| l.len()).max().unwrap();
+    if width < 3 || width.is_multiple_of(2) {
+        return Err("A drawing needs an odd number of columns, alternating dots and edges");
+    }
+    let (rows, cols) = (lines.len() / 2, width / 2);
+    let at = |line: usize, i: usize| lines[line].get(i).copied().unwrap_or(' ');
+    let fence = |c: char, on: char| match c {
+        ' ' => Ok(Fence(None)),
+        CROSS => Ok(Fence(Some(false))),
+        c if c == on => Ok(Fence(Some(true))),
+        _ => Err("Not a valid char for an edge in a drawing"),
+    };
+
+    let mut fences = Fences::new(rows, cols);
+    let mut tasks = Tasks::new(rows, cols);
+    for row in 0..=rows {
+        for col in 0..cols {
+            fences[Edge::horizontal(row, col)] = fence(at(2 * row, 2 * col + 1), BOX_HORIZONTAL)?;
+        }
+    }
+    for row in 0..rows {
+        for col in 0..=cols {
+            fences[Edge::vertical(row, col)] = fence(at(2 * row + 1, 2 * col), BOX_VERTICAL)?;
+        }
+        for col in 0..cols {
+            tasks[(row, col)] = match at(2 * row + 1, 2 * col + 1) {
+                ' ' => None,
+                c => Some(c.to_digit(10).ok_or("Not a valid clue in a drawing")? as u8),
+            };
+        }
+    }
+    Ok(Board {
+        fences,
+        tasks,
+        moves: vec![],
+    })
+}
+
 impl core::str::FromStr for Board {
     type Err = &'static str;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         if s.is_empty() {
             return Err("Cannot Parse Puzzle from empty string");
         }
+        if is_drawing(s) {
+            return parse_drawing(s);
+        }
         if s.contains('#') {
-            let mut mat = s.lines();
-            let mut head = mat.next().expect("Header missing").split('#');
-            let cols: usize = head.next().unwrap().parse().unwrap();
-            let task = Tasks::from_vec(
-                head.next()
-                    .unwrap()
-                    .chars()
-                    .map(|x| x.to_string().parse().ok())
-                    .collect(),
-                cols,
-            );
-            let mut board = Board {
-                fences: [
-                    Grid::<Fence>::new(task.rows() + 1, task.cols()),
-                    Grid::<Fence>::new(task.rows(), task.cols() + 1),
-                ],
-                tasks: task,
-                moves: vec![],
-            };
+            let mut mat = s.lines().map(|l| l.trim_end_matches('\r'));
+            let mut head = mat.next().ok_or("Header missing")?.split('#');
+            let cols: usize = head
+                .next()
+                .and_then(|c| c.trim().parse().ok())
+                .filter(|&c| c > 0)
+                .ok_or("Not a valid number of columns in the header")?;
+            let clues: Vec<_> = head
+                .next()
+                .ok_or("Header missing")?
+                .chars()
+                .map(|x| x.to_string().parse().ok())
+                .collect();
+            if clues.is_empty() || clues.len() % cols != 0 {
+                return Err("The clues don't fill the rows of the header");
+            }
+            let mut board = Board::new(Tasks::from_vec(clues, cols));
             for l in mat {
                 if l.is_empty() {
                     continue;
                 }
-                if l.starts_with(|c| matches!(c, '0' | '1')) {
+                if l.starts_with(['0', '1']) {
                     let mut m = l.split_whitespace();
-                    let dir = m.next().unwrap().parse().unwrap();
-                    let row = m.next().unwrap().parse().unwrap();
-                    let col = m.next().unwrap().parse().unwrap();
-                    let val = match m.next().unwrap() {
-                        "y" | "-" => true,
-                        "n" | "x" => false,
-                        _ => unreachable!("Invalid value"),
+                    let mut num = || -> Result<usize, &'static str> {
+                        m.next()
+                            .and_then(|x| x.parse().ok())
+                            .ok_or("Not a valid move in the puzzle")
                     };
-                    board.play(dir, (row, col), val, "".to_string());
+                    let dir = num()?.try_into()?;
+                    let (row, col) = (num()?, num()?);
+                    let val = match m.next() {
+                        Some("y" | "-") => true,
+                        Some("n" | "x") => false,
+                        _ => return Err("Not a valid value of a move in the puzzle"),
+                    };
+                    let edge = Edge(dir, row, col);
+                    if !board.has_edge(edge) {
+                        return Err("A move of the puzzle is off the board");
+                    }
+                    board.play(edge, val, MoveSource::Import);
                 } else {
+                    if l.chars().count() != board.fences_iter().count()
+                        || l.chars().any(|c| Fence::try_from(c).is_err())
+                    {
+                        return Err("Not a valid solution for the puzzle");
+                    }
                     board.set_solution(l)
                 }
             }
             Ok(board)
         } else {
-            let mat: Vec<_> = s.lines().collect();
-            assert!(mat.iter().all(|l| l.len() == mat.last().unwrap().len()));
+            let mat: Vec<Vec<_>> = s
+                .lines()
+                .map(|l| l.trim_end_matches('\r').chars().collect())
+                .collect();
+            let cols = mat[0].len();
+            if cols == 0 || mat.iter().any(|l| l.len() != cols) {
+                return Err("The rows of the puzzle differ in length");
+            }
             let tasks = Tasks::from_vec(
-                mat.join("")
-                    .chars()
+                mat.concat()
+                    .into_iter()
                     .map(|x| u8::from_str(&x.to_string()).ok())
                     .collect(),
-                mat[0].len(),
+                cols,
             );
-            let board = Board {
-                fences: [
-                    Grid::<Fence>::new(tasks.rows() + 1, tasks.cols()),
-                    Grid::<Fence>::new(tasks.rows(), tasks.cols() + 1),
-                ],
-                tasks,
-                moves: vec![],
-            };
-
-            Ok(board)
+            Ok(Board::new(tasks))
         }
     }
 }
@@ -498,6 +771,32 @@ mod tests {
         assert!("".parse::<Board>().is_err());
     }
     #[test]
+    fn check_drawing_parse() {
+        for s in [
+            "2#32  \n---xx--x-x--",
+            "3#4  \n-.-x.-----",
+            "4#1  0    1 21 23 \nnynnnnnnnnynnynnnnynnyynnnyynnnynynnnyyn",
+        ] {
+            let board: Board = s.parse().unwrap();
+            for drawing in [
+                print_board(&board.tasks, &board.fences, false),
+                print_board(&board.tasks, &board.fences, true),
+                format!("{board}"),
+            ] {
+                let parsed = parse_drawing(&drawing).unwrap();
+                assert_eq!(parsed.tasks, board.tasks);
+                assert_eq!(parsed.fences, board.fences);
+            }
+        }
+        let board: Board = "∙ ∙ ∙\n  ×  \n∙ ∙×∙\n  ×  \n∙ ∙ ∙".parse().unwrap();
+        assert_eq!(board.size(), (2, 2));
+        assert_eq!(board.fences[Edge::horizontal(1, 1)], Fence(Some(false)));
+        assert_eq!(board.fences[Edge::vertical(0, 1)], Fence(Some(false)));
+        assert_eq!(board.fences[Edge::vertical(1, 1)], Fence(Some(false)));
+        assert!(parse_drawing("∙─∙\n│a│\n∙─∙").is_err());
+        assert!(parse_drawing("∙─∙\n│ │").is_err());
+    }
+    #[test]
     fn check_board_result() {
         for (board, result) in [
             ("2#32  ", None),
@@ -523,4 +822,153 @@ nynnnnnnnnynnynnnnynnyynnnyynnnynynnnyyn",
             assert_eq!(board.parse::<Board>().unwrap().result(), result);
         }
     }
+    #[test]
+    fn check_player_moves() {
+        let mut board: Board = "2#32  ".parse().unwrap();
+        board.play(Edge::horizontal(0, 0), true, MoveSource::Player);
+        board.play(
+            Edge::horizontal(0, 1),
+            true,
+            MoveSource::Rule {
+                id: 3,
+                at: Cell(0, 0),
+            },
+        );
+        board.play(Edge::vertical(0, 0), true, MoveSource::Closure);
+        board.play(Edge::vertical(0, 2), true, MoveSource::Player);
+        board.play(Edge::vertical(1, 2), false, MoveSource::Search);
+        assert_eq!(board.player_moves().count(), 2);
+        let sources: Vec<_> = board.deductions(0).iter().map(|m| m.source).collect();
+        assert_eq!(
+            sources,
+            [
+                MoveSource::Rule {
+                    id: 3,
+                    at: Cell(0, 0)
+                },
+                MoveSource::Closure
+            ]
+        );
+        assert_eq!(board.deductions(1).len(), 1);
+        assert!(board.deductions(2).is_empty());
+        let undone = board.undo_player_move().unwrap();
+        assert_eq!(undone.len(), 2);
+        assert_eq!(undone[0].source, MoveSource::Player);
+        assert_eq!(board.moves().len(), 3);
+        assert!(board.edge(Edge::vertical(0, 2)).is_none());
+        board.undo_player_move().unwrap();
+        assert!(board.moves().is_empty());
+        assert!(board.undo_player_move().is_none());
+    }
+    #[test]
+    fn check_try_play() {
+        let mut board: Board = "2#0   ".parse().unwrap();
+        let mut play = |v| board.try_play(Edge::horizontal(0, 0), v, MoveSource::Player);
+        assert_eq!(play(true), Ok(Changed::Set));
+        assert_eq!(play(true), Ok(Changed::Unchanged));
+        let conflict = play(false).unwrap_err();
+        assert_eq!(conflict.by.unwrap().source, MoveSource::Player);
+        assert_eq!(**board.edge(Edge::horizontal(0, 0)), Some(true));
+        let rules = crate::rules::BoardRule::default_rules();
+        let conflict = crate::solver::solve_with(&mut board, &rules).unwrap_err();
+        assert_eq!(conflict.edge, Edge::horizontal(0, 0));
+        assert!(matches!(conflict.source, MoveSource::Rule { .. }));
+    }
+    #[test]
+    fn check_snapshot() {
+        let mut board: Board = "2#32  \n0 0 0 y".parse().unwrap();
+        let snapshot = board.snapshot();
+        board.play(Edge::vertical(0, 0), true, MoveSource::Search);
+        board.play(
+            Edge::horizontal(1, 1),
+            false,
+            MoveSource::Rule {
+                id: 2,
+                at: Cell(0, 0),
+            },
+        );
+        board.restore(snapshot);
+        assert_eq!(board.moves().len(), 1);
+        assert!(board.edge(Edge::vertical(0, 0)).is_none());
+        assert!(board.edge(Edge::horizontal(1, 1)).is_none());
+        assert_eq!(**board.edge(Edge::horizontal(0, 0)), Some(true));
+    }
+    #[test]
+    fn check_clear() {
+        let mut board: Board = "2#32  ".parse().unwrap();
+        board.play(Edge::horizontal(0, 0), true, MoveSource::Player);
+        board.play(
+            Edge::vertical(0, 0),
+            true,
+            MoveSource::Rule {
+                id: 0,
+                at: Cell(0, 0),
+            },
+        );
+        board.play(Edge::horizontal(0, 1), true, MoveSource::Player);
+        board.play(
+            Edge::vertical(0, 2),
+            true,
+            MoveSource::Rule {
+                id: 0,
+                at: Cell(0, 0),
+            },
+        );
+        assert!(board.clear(Edge::vertical(1, 1)).is_none());
+        let removed = board.clear(Edge::horizontal(0, 0)).unwrap();
+        assert_eq!(removed.len(), 3);
+        assert_eq!(removed[0].edge, Edge::horizontal(0, 0));
+        assert_eq!(board.moves().len(), 1);
+        assert_eq!(**board.edge(Edge::horizontal(0, 1)), Some(true));
+        assert!(board.edge(Edge::vertical(0, 2)).is_none());
+    }
+    #[test]
+    fn check_validate() {
+        let violations = |s: &str| s.parse::<Board>().unwrap().validate();
+        assert!(violations("2#32  \n---..--.-.--").is_empty());
+        assert!(violations("2#32  \n...-...-....").is_empty());
+        assert!(matches!(
+            &violations("2#32  \n-.....x.....")[..],
+            [Violation::DeadEnd { dot: Dot(0, 0), .. }]
+        ));
+        assert!(matches!(
+            &violations("2#    \n--.....-....")[..],
+            [Violation::Branch { dot: Dot(0, 1), edges }] if edges.len() == 3
+        ));
+        assert!(matches!(
+            &violations("2#1   \n-.-...--....")[..],
+            [Violation::Over { cell: Cell(0, 0), edges }] if edges.len() == 4
+        ));
+        assert!(matches!(
+            &violations("2#3   \nx.....x.....")[..],
+            [Violation::Under {
+                cell: Cell(0, 0),
+                ..
+            }]
+        ));
+        let loops = violations("3#      \n-..-....---......");
+        assert!(matches!(&loops[..], [Violation::Loop { edges }] if edges.len() == 4));
+        let split = violations("4#        \n-xx--xx-xxxx--x--xxxxx");
+        assert!(split
+            .iter()
+            .any(|v| matches!(v, Violation::Disconnected { paths } if paths.len() == 2)));
+    }
+    #[test]
+    fn check_mistakes() {
+        let solved: Board = "2#32  \n---..--.-.--".parse().unwrap();
+        let solution = solved.solution();
+        let mut board: Board = "2#32  ".parse().unwrap();
+        board.play(Edge::horizontal(0, 0), true, MoveSource::Player);
+        board.play(Edge::vertical(1, 0), true, MoveSource::Player);
+        board.play(
+            Edge::vertical(0, 2),
+            false,
+            MoveSource::Rule {
+                id: 0,
+                at: Cell(0, 0),
+            },
+        );
+        let wrong: Vec<_> = board.mistakes(&solution).map(|(i, _)| i).collect();
+        assert_eq!(wrong, [1, 2]);
+    }
 }
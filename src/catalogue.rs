@@ -0,0 +1,129 @@
+use crate::{
+    rules::BoardRule,
+    svg::{svg_board, SvgOptions},
+};
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CatalogueFormat {
+    Markdown,
+    Html,
+}
+
+impl CatalogueFormat {
+    /// `.html`/`.htm` files get html, anything else markdown.
+    pub fn from_path(path: &str) -> Self {
+        if path.ends_with(".html") || path.ends_with(".htm") {
+            CatalogueFormat::Html
+        } else {
+            CatalogueFormat::Markdown
+        }
+    }
+}
+
+fn clues(rule: &BoardRule) -> String {
+    rule.task
+        .iter_rows()
+        .map(|r| r.map(|c| c.map_or('.', |x| (b'0' + x) as char)).collect())
+        .collect::<Vec<String>>()
+        .join("/")
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn rule_entry(
+    f: &mut String,
+    rule: &BoardRule,
+    title: &str,
+    level: usize,
+    format: CatalogueFormat,
+) {
+    let opts = SvgOptions {
+        cell_size: 30.,
+        crosses: true,
+        color: false,
+    };
+    let before = svg_board(&rule.task, &rule.fences, &opts);
+    let after = svg_board(&rule.task, &rule.solution, &opts);
+    let diagram = format!("{rule}");
+    match format {
+        CatalogueFormat::Markdown => {
+            // Code blocks show their text as it is, only escape outside them.
+            let h = "#".repeat(level);
+            write!(
+                f,
+                "{h} {}\n\nClues: `{}`, applies: {}\n\n<p>\n{before}&rarr;\n{after}</p>\n\n```text\n{diagram}```\n\n",
+                escape(title),
+                clues(rule),
+                escape(&rule.variant.to_string())
+            )
+            .unwrap();
+        }
+        CatalogueFormat::Html => {
+            let h = format!("h{level}");
+            write!(
+                f,
+                "<{h}>{}</{h}>\n<p>Clues: <code>{}</code>, applies: {}</p>\n<p>\n{before}&rarr;\n{after}</p>\n<pre>{}</pre>\n",
+                escape(title),
+                clues(rule),
+                escape(&rule.variant.to_string()),
+                escape(&diagram)
+            )
+            .unwrap();
+        }
+    }
+}
+
+/// Lists every rule with its context and a before/after image, optionally
+/// followed by each of its distinct rotations.
+pub fn rule_catalogue(rules: &[BoardRule], format: CatalogueFormat, rotations: bool) -> String {
+    let mut f = match format {
+        CatalogueFormat::Markdown => format!("# Rule catalogue\n\n{} rules.\n\n", rules.len()),
+        CatalogueFormat::Html => format!(
+            "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Rule catalogue</title></head>\n<body>\n<h1>Rule catalogue</h1>\n<p>{} rules.</p>\n",
+            rules.len()
+        ),
+    };
+    for (i, rule) in rules.iter().enumerate() {
+        rule_entry(&mut f, rule, &format!("Rule {i}"), 2, format);
+        if rotations {
+            for (r, rot) in rule.get_rotations().iter().enumerate().skip(1) {
+                rule_entry(&mut f, rot, &format!("Rotation {i}.{r}"), 3, format);
+            }
+        }
+    }
+    if format == CatalogueFormat::Html {
+        f += "</body>\n</html>\n";
+    }
+    f
+}
+
+pub fn write_rule_catalogue(rules: &[BoardRule], out: &str, rotations: bool) -> anyhow::Result<()> {
+    std::fs::write(
+        out,
+        rule_catalogue(rules, CatalogueFormat::from_path(out), rotations),
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn check_rule_catalogue() {
+        let rules = BoardRule::default_base_rules();
+        let md = rule_catalogue(&rules, CatalogueFormat::Markdown, false);
+        assert_eq!(md.matches("\n## Rule ").count(), rules.len());
+        assert_eq!(md.matches("<svg").count(), 2 * rules.len());
+        let diagram = format!("{}", rules[0]);
+        assert!(md.contains(&format!("```text\n{diagram}```")));
+        let html = rule_catalogue(&rules, CatalogueFormat::Html, true);
+        let rotations: usize = rules.iter().map(|r| r.get_rotations().len()).sum();
+        assert_eq!(html.matches("<svg").count(), 2 * rotations);
+        assert!(html.ends_with("</html>\n"));
+    }
+}
@@ -0,0 +1,265 @@
+use crate::{
+    board::{is_drawing, print_board, Tasks},
+    Board, BoardGeom, FencesSolver, SaveGame,
+};
+use anyhow::{anyhow, bail, Result};
+use grid::Grid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PuzzleFormat {
+    /// `cols#clues`, optionally followed by move lines and a solution.
+    Header,
+    /// One line per row, `_` for cells without a clue.
+    Grid,
+    /// Janko rows, `-` for cells without a clue, optionally space separated.
+    Janko,
+    /// A puzz.link/pzv.jp `slither` url.
+    Url,
+    /// The box-drawing output of `print_board`.
+    Drawing,
+    /// A yaml save game.
+    Save,
+}
+
+impl PuzzleFormat {
+    pub const ALL: [PuzzleFormat; 6] = [
+        PuzzleFormat::Header,
+        PuzzleFormat::Grid,
+        PuzzleFormat::Janko,
+        PuzzleFormat::Url,
+        PuzzleFormat::Drawing,
+        PuzzleFormat::Save,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PuzzleFormat::Header => "header",
+            PuzzleFormat::Grid => "grid",
+            PuzzleFormat::Janko => "janko",
+            PuzzleFormat::Url => "url",
+            PuzzleFormat::Drawing => "drawing",
+            PuzzleFormat::Save => "save",
+        }
+    }
+
+    /// Guesses the format of a puzzle from its text.
+    pub fn detect(s: &str) -> Self {
+        let t = s.trim();
+        if t.starts_with("http://") || t.starts_with("https://") || t.contains("slither/") {
+            PuzzleFormat::Url
+        } else if is_drawing(s) {
+            PuzzleFormat::Drawing
+        } else if t.starts_with("version:") {
+            PuzzleFormat::Save
+        } else if t.lines().next().is_some_and(|l| l.contains('#')) {
+            PuzzleFormat::Header
+        } else if t.contains("[problem]") || t.contains('-') {
+            PuzzleFormat::Janko
+        } else {
+            PuzzleFormat::Grid
+        }
+    }
+}
+
+impl std::str::FromStr for PuzzleFormat {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|f| f.name() == s)
+            .ok_or(anyhow!("Unknown puzzle format `{s}`"))
+    }
+}
+
+impl std::fmt::Display for PuzzleFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Loads a puzzle in any of the supported formats.
+pub fn load_puzzle(s: &str) -> Result<Board> {
+    parse_puzzle(s, PuzzleFormat::detect(s))
+}
+
+pub fn parse_puzzle(s: &str, format: PuzzleFormat) -> Result<Board> {
+    match format {
+        PuzzleFormat::Header | PuzzleFormat::Grid | PuzzleFormat::Drawing => s
+            .trim_matches(['\n', '\r'])
+            .parse()
+            .map_err(anyhow::Error::msg),
+        PuzzleFormat::Janko => parse_janko(s),
+        PuzzleFormat::Url => parse_url(s),
+        PuzzleFormat::Save => {
+            let save = SaveGame::parse(s)?;
+            let mut board = save.board()?;
+            if let (true, Some(solution)) = (save.completed, &save.solution) {
+                board.set_solution(solution);
+            }
+            Ok(board)
+        }
+    }
+}
+
+pub fn write_puzzle(board: &Board, format: PuzzleFormat) -> Result<String> {
+    let clue = |t: &Option<u8>, empty: char| t.map_or(empty, |x| (b'0' + x) as char);
+    let rows = |sep: &str, empty: char| -> String {
+        board
+            .tasks()
+            .iter_rows()
+            .map(|r| {
+                r.map(|t| clue(t, empty).to_string())
+                    .collect::<Vec<_>>()
+                    .join(sep)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    Ok(match format {
+        PuzzleFormat::Header => {
+            let mut s = board.puzzle();
+            if board.fences_iter().any(|(_, f)| f.is_some()) {
+                s.push('\n');
+                s.extend(board.fences_iter().map(|(_, &f)| char::from(f)));
+            }
+            s
+        }
+        PuzzleFormat::Grid => rows("", '_'),
+        PuzzleFormat::Janko => rows(" ", '-'),
+        PuzzleFormat::Url => format!(
+            "https://puzz.link/p?slither/{}/{}/{}",
+            board.cols(),
+            board.rows(),
+            encode_url(board.tasks())
+        ),
+        PuzzleFormat::Drawing => print_board(board.tasks(), board.fences(), false),
+        PuzzleFormat::Save => serde_yaml::to_string(&SaveGame::new(board, None))?,
+    })
+}
+
+fn parse_janko(s: &str) -> Result<Board> {
+    let mut lines: Vec<&str> = s.lines().map(str::trim).collect();
+    if let Some(i) = lines.iter().position(|l| *l == "[problem]") {
+        lines.drain(..=i);
+        if let Some(j) = lines.iter().position(|l| l.starts_with('[')) {
+            lines.truncate(j);
+        }
+    }
+    let rows: Vec<Vec<_>> = lines
+        .iter()
+        .filter(|l| !l.is_empty())
+        .map(|l| {
+            let cells: Vec<String> = if l.contains(char::is_whitespace) {
+                l.split_whitespace().map(str::to_string).collect()
+            } else {
+                l.chars().map(|c| c.to_string()).collect()
+            };
+            cells.iter().map(|c| c.parse().ok()).collect()
+        })
+        .collect();
+    let cols = rows.first().map_or(0, |r| r.len());
+    if cols == 0 || rows.iter().any(|r| r.len() != cols) {
+        bail!("Janko puzzle rows must all have the same, non zero, length")
+    }
+    Ok(Board::new(Tasks::from_vec(rows.concat(), cols)))
+}
+
+fn parse_url(s: &str) -> Result<Board> {
+    let s = s.trim();
+    let rest = &s[s.find("slither/").ok_or(anyhow!("Not a slither url"))? + "slither/".len()..];
+    let mut parts = rest.split('/');
+    let mut next = || parts.next().ok_or(anyhow!("Incomplete slither url"));
+    let cols: usize = next()?.parse()?;
+    let rows: usize = next()?.parse()?;
+    let data = next()?;
+
+    let mut tasks = vec![None; rows * cols];
+    let mut c = 0;
+    for ch in data.chars() {
+        if c >= tasks.len() {
+            break;
+        }
+        let v = ch
+            .to_digit(36)
+            .ok_or(anyhow!("Invalid char `{ch}` in slither url"))? as usize;
+        match v {
+            0..=4 => tasks[c] = Some(v as u8),
+            5..=9 => {
+                tasks[c] = Some(v as u8 - 5);
+                c += 1
+            }
+            10..=14 => {
+                tasks[c] = Some(v as u8 - 10);
+                c += 2
+            }
+            16..=35 => c += v - 16,
+            _ => bail!("Invalid char `{ch}` in slither url"),
+        }
+        c += 1;
+    }
+    Ok(Board::new(Grid::from_vec(tasks, cols)))
+}
+
+fn encode_url(tasks: &Tasks) -> String {
+    let tasks: Vec<_> = tasks.iter().copied().collect();
+    let digit = |v: usize| char::from_digit(v as u32, 36).unwrap();
+    let mut ret = String::new();
+    let mut c = 0;
+    while c < tasks.len() {
+        if let Some(t) = tasks[c] {
+            let gap = tasks[c + 1..]
+                .iter()
+                .take(2)
+                .take_while(|t| t.is_none())
+                .count();
+            ret.push(digit(t as usize + 5 * gap));
+            c += gap + 1;
+        } else {
+            let gap = tasks[c..]
+                .iter()
+                .take(20)
+                .take_while(|t| t.is_none())
+                .count();
+            ret.push(digit(15 + gap));
+            c += gap;
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn check_puzzle_formats() {
+        let board = load_puzzle(&std::fs::read_to_string("puzzle/example.txt").unwrap()).unwrap();
+        assert_eq!(board.size(), (8, 8));
+        for format in PuzzleFormat::ALL {
+            let s = write_puzzle(&board, format).unwrap();
+            assert_eq!(PuzzleFormat::detect(&s), format, "{s}");
+            assert_eq!(load_puzzle(&s).unwrap().tasks(), board.tasks());
+        }
+        let janko = "[problem]\n- 3 - -\n2 - - 1\n[solution]\n";
+        assert_eq!(load_puzzle(janko).unwrap().puzzle(), "4# 3  2  1");
+        assert_eq!(
+            load_puzzle("https://puzz.link/p?slither/4/2/g3h2i")
+                .unwrap()
+                .puzzle(),
+            "4# 3  2   "
+        );
+        assert!(load_puzzle("https://puzz.link/p?slither/4/2/g#").is_err());
+        assert_eq!(load_puzzle("2#33\r\n").unwrap().puzzle(), "2#33");
+        assert_eq!(load_puzzle("33\r\n12\r\n").unwrap().size(), (2, 2));
+        for bad in [
+            "x#123",
+            "3#12",
+            "0#",
+            "2#33\n0 0 0",
+            "2#33\n0 0 9 y",
+            "2#33\n---x",
+            "123\n12\n",
+        ] {
+            assert!(load_puzzle(bad).is_err(), "{bad}");
+        }
+    }
+}
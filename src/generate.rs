@@ -0,0 +1,162 @@
+use crate::{
+    board::Tasks,
+    grade::MAX_GUESSES,
+    rules::BoardRule,
+    solver::{search, solve_with},
+    Board, FencesSolver,
+};
+use grid::Grid;
+
+/// xorshift64*, enough to shuffle cells reproducibly from a seed.
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed ^ 0x9e37_79b9_7f4a_7c15 | 1)
+    }
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+/// Grows a region of cells whose outline is a single loop.
+fn grow_region(rows: usize, cols: usize, rng: &mut Rng) -> Grid<bool> {
+    let mut region = Grid::new(rows, cols);
+    let at = |region: &Grid<bool>, r: isize, c: isize| {
+        r >= 0 && c >= 0 && region.get(r, c).is_some_and(|&x| x)
+    };
+    region[(rng.below(rows), rng.below(cols))] = true;
+    let target = rows * cols * (40 + rng.below(25)) / 100;
+    for _ in 1..target.max(1) {
+        let candidates: Vec<_> = region
+            .indexed_iter()
+            .filter(|(_, &x)| !x)
+            .map(|(idx, _)| idx)
+            .filter(|&(r, c)| {
+                let (r, c) = (r as isize, c as isize);
+                // Neighbours clockwise from north, the ones in the region
+                // have to be a single run so no holes or pinches appear.
+                let n = [
+                    (-1, 0),
+                    (-1, 1),
+                    (0, 1),
+                    (1, 1),
+                    (1, 0),
+                    (1, -1),
+                    (0, -1),
+                    (-1, -1),
+                ]
+                .map(|(dr, dc)| at(&region, r + dr, c + dc));
+                let runs = (0..8).filter(|&i| n[i] && !n[(i + 7) % 8]).count();
+                runs == 1 && [0, 2, 4, 6].iter().any(|&i| n[i])
+            })
+            .collect();
+        if candidates.is_empty() {
+            break;
+        }
+        region[candidates[rng.below(candidates.len())]] = true;
+    }
+    region
+}
+
+/// The solved board whose loop is the outline of `region`.
+fn outline(region: &Grid<bool>) -> Board {
+    let (rows, cols) = region.size();
+    let at = |r: usize, c: usize| region.get(r, c).is_some_and(|&x| x);
+    let mut tasks = Tasks::new(rows, cols);
+    let mut solution = String::new();
+    for r in 0..=rows {
+        for c in 0..cols {
+            let up = r > 0 && at(r - 1, c);
+            solution.push(if up != at(r, c) { 'y' } else { 'n' });
+        }
+    }
+    for r in 0..rows {
+        for c in 0..=cols {
+            let left = c > 0 && at(r, c - 1);
+            solution.push(if left != at(r, c) { 'y' } else { 'n' });
+        }
+    }
+    for ((r, c), t) in tasks.indexed_iter_mut() {
+        let n = [
+            r > 0 && at(r - 1, c),
+            at(r + 1, c),
+            c > 0 && at(r, c - 1),
+            at(r, c + 1),
+        ];
+        *t = Some(n.iter().filter(|&&x| x != at(r, c)).count() as u8);
+    }
+    let mut board = Board::new(tasks);
+    board.set_solution(&solution);
+    board
+}
+
+/// Whether the clues of `board` have exactly one solution. With `rules_only`
+/// the rules have to find it without guessing.
+pub fn is_unique(board: &Board, rules: &[BoardRule], rules_only: bool) -> bool {
+    let mut b = board.clone();
+    if solve_with(&mut b, rules).is_err() {
+        return false;
+    }
+    match b.result() {
+        Some(true) => true,
+        Some(false) => false,
+        None if rules_only => false,
+        None => {
+            let res = search(&b, rules, 2, MAX_GUESSES);
+            res.complete && res.solutions.len() == 1
+        }
+    }
+}
+
+/// Generates a `rows` x `cols` puzzle with a unique solution, removing clues
+/// as long as the solution stays unique.
+pub fn generate(
+    rows: usize,
+    cols: usize,
+    seed: u64,
+    rules: &[BoardRule],
+    rules_only: bool,
+) -> Board {
+    assert!(rows > 0 && cols > 0, "A puzzle needs at least one cell");
+    let rng = &mut Rng::new(seed);
+    let solved = loop {
+        let b = outline(&grow_region(rows, cols, rng));
+        if b.result() == Some(true) {
+            break b;
+        }
+    };
+    let mut tasks = solved.tasks().clone();
+    let mut cells: Vec<_> = tasks.indexed_iter().map(|(idx, _)| idx).collect();
+    for i in (1..cells.len()).rev() {
+        cells.swap(i, rng.below(i + 1));
+    }
+    for idx in cells {
+        let clue = tasks[idx].take();
+        if !is_unique(&Board::new(tasks.clone()), rules, rules_only) {
+            tasks[idx] = clue;
+        }
+    }
+    Board::new(tasks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn check_generate() {
+        let rules = BoardRule::default_rules();
+        for seed in 0..3 {
+            let board = generate(4, 4, seed, &rules, true);
+            assert!(board.tasks().iter().any(|t| t.is_none()));
+            assert!(is_unique(&board, &rules, true));
+        }
+    }
+}
@@ -1,3 +1,7 @@
+use crate::solver::Idx;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
 pub trait BoardGeom {
     fn rotate(&mut self);
     fn size(&self) -> (usize, usize);
@@ -9,4 +13,224 @@ pub trait BoardGeom {
     fn cols(&self) -> usize {
         self.size().1
     }
+    /// Whether edge `e` is on the board.
+    fn has_edge(&self, Edge(dir, row, col): Edge) -> bool {
+        let (rows, cols) = self.size();
+        match dir {
+            Direction::Horizontal => row <= rows && col < cols,
+            Direction::Vertical => row < rows && col <= cols,
+        }
+    }
+    /// The edges meeting at `dot`: right, down, left, up, leaving out the
+    /// ones off the board.
+    fn dot_edges(&self, dot: Dot) -> Vec<Edge> {
+        dot.edges()
+            .into_iter()
+            .flatten()
+            .filter(|&e| self.has_edge(e))
+            .collect()
+    }
+    /// The edges around `cell`: top, left, bottom, right. `cell` must be on
+    /// the board, which is only checked in debug builds.
+    fn cell_edges(&self, cell: Cell) -> [Edge; 4] {
+        debug_assert!(
+            cell.0 < self.rows() && cell.1 < self.cols(),
+            "Cell {cell} is off the board"
+        );
+        cell.edges()
+    }
+    /// The cells on the board on either side of `e`, one for the edges on
+    /// the border.
+    fn edge_cells(&self, e: Edge) -> Vec<Cell> {
+        let (rows, cols) = self.size();
+        e.cells()
+            .into_iter()
+            .flatten()
+            .filter(|&Cell(r, c)| r < rows && c < cols)
+            .collect()
+    }
+    /// The dots at the ends of `e`, top or left one first. `e` must be on
+    /// the board, which is only checked in debug builds.
+    fn edge_dots(&self, e: Edge) -> [Dot; 2] {
+        debug_assert!(self.has_edge(e), "Edge {e} is off the board");
+        e.dots()
+    }
+}
+
+/// Which way an edge runs, written `0` and `1` in puzzle files, moves and
+/// saves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(into = "usize", try_from = "usize")]
+pub enum Direction {
+    Horizontal,
+    Vertical,
+}
+
+impl TryFrom<usize> for Direction {
+    type Error = &'static str;
+    fn try_from(value: usize) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Direction::Horizontal),
+            1 => Ok(Direction::Vertical),
+            _ => Err("Direction is 0 or 1"),
+        }
+    }
+}
+
+impl From<Direction> for usize {
+    fn from(value: Direction) -> usize {
+        value as usize
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", *self as usize)
+    }
+}
+
+/// A dot at `(row, col)`, where edges meet. `Dot(0, 0)` is the top left
+/// corner of the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Dot(pub usize, pub usize);
+
+/// A cell at `(row, col)`, where the clues are. Its top left corner is
+/// `Dot(row, col)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Cell(pub usize, pub usize);
+
+/// The edge leaving dot `(row, col)` to the right when horizontal, down
+/// when vertical.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Edge(pub Direction, pub usize, pub usize);
+
+impl Edge {
+    pub fn horizontal(row: usize, col: usize) -> Self {
+        Edge(Direction::Horizontal, row, col)
+    }
+    pub fn vertical(row: usize, col: usize) -> Self {
+        Edge(Direction::Vertical, row, col)
+    }
+    /// The dots at its ends, top or left one first.
+    pub fn dots(self) -> [Dot; 2] {
+        let Edge(dir, row, col) = self;
+        match dir {
+            Direction::Horizontal => [Dot(row, col), Dot(row, col + 1)],
+            Direction::Vertical => [Dot(row, col), Dot(row + 1, col)],
+        }
+    }
+    /// The cells on either side, above or left first. Either may be off the
+    /// board: the first is `None` on the top and left borders, the second
+    /// is past the last row or column on the bottom and right ones.
+    pub fn cells(self) -> [Option<Cell>; 2] {
+        let Edge(dir, row, col) = self;
+        match dir {
+            Direction::Horizontal => [
+                row.checked_sub(1).map(|r| Cell(r, col)),
+                Some(Cell(row, col)),
+            ],
+            Direction::Vertical => [
+                col.checked_sub(1).map(|c| Cell(row, c)),
+                Some(Cell(row, col)),
+            ],
+        }
+    }
+    /// Whether the two edges share a dot.
+    pub fn is_linked(self, other: Edge) -> bool {
+        self != other && self.dots().iter().any(|d| other.dots().contains(d))
+    }
+    /// The same edge moved down `row` and right `col`.
+    pub fn offset(self, (row, col): Idx) -> Self {
+        Edge(self.0, self.1 + row, self.2 + col)
+    }
+    /// Where the edge goes when a board of `rows` rows turns a quarter
+    /// clockwise.
+    pub fn rotate(self, rows: usize) -> Self {
+        let Edge(dir, row, col) = self;
+        match dir {
+            Direction::Horizontal => Edge::vertical(col, rows - row),
+            Direction::Vertical => Edge::horizontal(col, rows - 1 - row),
+        }
+    }
+}
+
+impl Dot {
+    /// The edges at the dot: right, down, left, up. Left and up are `None`
+    /// on the left and top borders; right and down may be off the board.
+    pub fn edges(self) -> [Option<Edge>; 4] {
+        let Dot(row, col) = self;
+        [
+            Some(Edge::horizontal(row, col)),
+            Some(Edge::vertical(row, col)),
+            col.checked_sub(1).map(|c| Edge::horizontal(row, c)),
+            row.checked_sub(1).map(|r| Edge::vertical(r, col)),
+        ]
+    }
+}
+
+impl Cell {
+    /// The edges around the cell: top, left, bottom, right.
+    pub fn edges(self) -> [Edge; 4] {
+        let Cell(row, col) = self;
+        [
+            Edge::horizontal(row, col),
+            Edge::vertical(row, col),
+            Edge::horizontal(row + 1, col),
+            Edge::vertical(row, col + 1),
+        ]
+    }
+    /// The same cell moved down `row` and right `col`.
+    pub fn offset(self, (row, col): Idx) -> Self {
+        Cell(self.0 + row, self.1 + col)
+    }
+}
+
+macro_rules! idx_conversions {
+    ($a:ident) => {
+        impl From<Idx> for $a {
+            fn from((row, col): Idx) -> Self {
+                $a(row, col)
+            }
+        }
+        impl From<$a> for Idx {
+            fn from(value: $a) -> Idx {
+                (value.0, value.1)
+            }
+        }
+        impl fmt::Display for $a {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{} {}", self.0, self.1)
+            }
+        }
+    };
+}
+idx_conversions! {Dot}
+idx_conversions! {Cell}
+
+impl fmt::Display for Edge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.0, self.1, self.2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn check_geom() {
+        let e = Edge::vertical(1, 2);
+        assert_eq!(e.dots(), [Dot(1, 2), Dot(2, 2)]);
+        assert_eq!(e.cells(), [Some(Cell(1, 1)), Some(Cell(1, 2))]);
+        assert_eq!(Edge::horizontal(0, 1).cells()[0], None);
+        assert!(e.is_linked(Edge::horizontal(2, 1)));
+        assert!(!e.is_linked(e));
+        assert!(!e.is_linked(Edge::vertical(3, 2)));
+        assert_eq!(Dot(0, 3).edges()[2..], [Some(Edge::horizontal(0, 2)), None]);
+        assert!(Cell(1, 2)
+            .edges()
+            .iter()
+            .all(|c| c.cells().contains(&Some(Cell(1, 2)))));
+        assert_eq!(e.offset((1, 0)).to_string(), "1 2 2");
+        assert_eq!(Direction::try_from(2), Err("Direction is 0 or 1"));
+    }
 }
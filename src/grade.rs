@@ -0,0 +1,74 @@
+use crate::{
+    rules::BoardRule,
+    solver::{search, solve_with},
+    Board,
+};
+
+/// Guesses allowed when grading before a puzzle is called too hard to tell.
+pub const MAX_GUESSES: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Difficulty {
+    /// Solved by the rules in a couple of rounds.
+    Easy,
+    Medium,
+    /// Solved by the rules alone, but only after many rounds.
+    Hard,
+    /// Needs guessing on top of the rules.
+    Expert,
+    /// Has more than one solution.
+    Ambiguous,
+    /// Has no solution.
+    Invalid,
+    /// The search gave up before deciding.
+    Unknown,
+}
+
+impl std::fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", format!("{self:?}").to_lowercase())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Grade {
+    pub difficulty: Difficulty,
+    /// Rounds of rule propagation on the puzzle as given.
+    pub rounds: usize,
+    /// Edges decided by the rules before any guessing.
+    pub deductions: usize,
+    pub guesses: usize,
+}
+
+pub fn grade(board: &Board, rules: &[BoardRule]) -> Grade {
+    let mut b = board.clone();
+    let before = b.moves().len();
+    let solved = solve_with(&mut b, rules);
+    let deductions = b.moves().len() - before;
+    let rounds = *solved.as_ref().unwrap_or(&0);
+    let (difficulty, guesses) = match b.result() {
+        _ if solved.is_err() => (Difficulty::Invalid, 0),
+        Some(true) if rounds <= 3 => (Difficulty::Easy, 0),
+        Some(true) if rounds <= 6 => (Difficulty::Medium, 0),
+        Some(true) => (Difficulty::Hard, 0),
+        Some(false) => (Difficulty::Invalid, 0),
+        None => {
+            let res = search(&b, rules, 2, MAX_GUESSES);
+            (
+                match res.solutions.len() {
+                    0 if res.complete => Difficulty::Invalid,
+                    1 if res.complete => Difficulty::Expert,
+                    2.. => Difficulty::Ambiguous,
+                    _ => Difficulty::Unknown,
+                },
+                res.nodes,
+            )
+        }
+    };
+    Grade {
+        difficulty,
+        rounds,
+        deductions,
+        guesses,
+    }
+}
@@ -1,5 +1,8 @@
-use crate::BoardGeom;
-use std::fmt;
+use crate::geom::{BoardGeom, Direction, Edge};
+use std::{
+    fmt,
+    ops::{Index, IndexMut},
+};
 
 #[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
 pub struct Fence(pub(crate) Option<bool>);
@@ -52,17 +55,184 @@ macro_rules! deref_impls {
 }
 deref_impls! {Fence, Option<bool>}
 
-struct Fences {
-    cols: usize,
+/// Position of an edge in [`Fences`]: the horizontal edges row by row, then
+/// the vertical ones, the order of [`crate::Board::solution`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EdgeId(pub usize);
+
+/// The edges of a `rows` x `cols` board.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Fences {
     rows: usize,
-    _fences: Vec<Fence>,
+    cols: usize,
+    fences: Vec<Fence>,
+}
+
+impl Fences {
+    /// Every edge undecided.
+    pub fn new(rows: usize, cols: usize) -> Self {
+        Self::from_vec(
+            rows,
+            cols,
+            vec![Fence::default(); 2 * rows * cols + rows + cols],
+        )
+    }
+    /// Takes the edges in [`EdgeId`] order.
+    pub fn from_vec(rows: usize, cols: usize, fences: Vec<Fence>) -> Self {
+        assert_eq!(
+            fences.len(),
+            2 * rows * cols + rows + cols,
+            "Wrong number of fences for a {rows}x{cols} board"
+        );
+        Self { rows, cols, fences }
+    }
+    pub fn len(&self) -> usize {
+        self.fences.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.fences.is_empty()
+    }
+    #[inline]
+    pub fn id(&self, e: Edge) -> EdgeId {
+        debug_assert!(self.has_edge(e), "No edge {e}");
+        edge_id((self.rows, self.cols), e)
+    }
+    #[inline]
+    pub fn edge(&self, id: EdgeId) -> Edge {
+        edge_at((self.rows, self.cols), id)
+    }
+    pub fn get(&self, e: Edge) -> Option<&Fence> {
+        self.has_edge(e).then(|| &self[e])
+    }
+    /// The edges with their values, in [`EdgeId`] order.
+    pub fn iter(&self) -> impl Iterator<Item = (Edge, &Fence)> {
+        (0..self.len()).map(|id| (self.edge(EdgeId(id)), &self.fences[id]))
+    }
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Edge, &mut Fence)> {
+        let size = self.size();
+        self.fences
+            .iter_mut()
+            .enumerate()
+            .map(move |(id, f)| (edge_at(size, EdgeId(id)), f))
+    }
+}
+
+/// The [`EdgeId`] of `e` on a `(rows, cols)` board.
+#[inline]
+pub(crate) fn edge_id((rows, cols): (usize, usize), Edge(dir, row, col): Edge) -> EdgeId {
+    EdgeId(match dir {
+        Direction::Horizontal => row * cols + col,
+        Direction::Vertical => (rows + 1) * cols + row * (cols + 1) + col,
+    })
+}
+
+/// The edge numbered `id` on a `(rows, cols)` board.
+#[inline]
+pub(crate) fn edge_at((rows, cols): (usize, usize), EdgeId(id): EdgeId) -> Edge {
+    let b = (rows + 1) * cols;
+    if id < b {
+        Edge::horizontal(id / cols, id % cols)
+    } else {
+        let id = id - b;
+        Edge::vertical(id / (cols + 1), id % (cols + 1))
+    }
 }
 
 impl BoardGeom for Fences {
     fn size(&self) -> (usize, usize) {
         (self.rows, self.cols)
     }
+    /// Turns the edges a quarter clockwise, as `Grid::rotate_right` turns
+    /// the clues.
     fn rotate(&mut self) {
-        unimplemented!()
+        let mut ret = Fences::new(self.cols, self.rows);
+        for (e, &f) in self.iter() {
+            ret[e.rotate(self.rows)] = f;
+        }
+        *self = ret;
+    }
+}
+
+impl Index<Edge> for Fences {
+    type Output = Fence;
+    fn index(&self, e: Edge) -> &Fence {
+        &self.fences[self.id(e).0]
+    }
+}
+impl IndexMut<Edge> for Fences {
+    fn index_mut(&mut self, e: Edge) -> &mut Fence {
+        let id = self.id(e).0;
+        &mut self.fences[id]
+    }
+}
+impl Index<EdgeId> for Fences {
+    type Output = Fence;
+    fn index(&self, id: EdgeId) -> &Fence {
+        &self.fences[id.0]
+    }
+}
+impl IndexMut<EdgeId> for Fences {
+    fn index_mut(&mut self, id: EdgeId) -> &mut Fence {
+        &mut self.fences[id.0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geom::{Cell, Dot};
+    use grid::Grid;
+    #[test]
+    fn check_fences() {
+        let values: Vec<Fence> = "y.nny.n..y.yn.yy."
+            .chars()
+            .map(|c| c.try_into().unwrap())
+            .collect();
+        let mut fences = Fences::from_vec(2, 3, values.clone());
+        assert!(fences
+            .iter()
+            .all(|(e, f)| fences.edge(fences.id(e)) == e && fences[e] == *f));
+        assert_eq!(fences[Edge::vertical(0, 0)], fences[EdgeId(9)]);
+        assert_eq!(fences.get(Edge::horizontal(3, 0)), None);
+        assert_eq!(
+            fences.dot_edges(Dot(0, 0)),
+            [Edge::horizontal(0, 0), Edge::vertical(0, 0)]
+        );
+        assert_eq!(fences.dot_edges(Dot(2, 3)).len(), 2);
+        assert_eq!(fences.dot_edges(Dot(1, 1)).len(), 4);
+        assert_eq!(fences.edge_cells(Edge::horizontal(0, 2)), [Cell(0, 2)]);
+        assert_eq!(
+            fences.edge_cells(Edge::vertical(1, 1)),
+            [Cell(1, 0), Cell(1, 1)]
+        );
+        assert_eq!(fences.edge_cells(Edge::vertical(1, 3)), [Cell(1, 2)]);
+        assert_eq!(
+            fences.edge_dots(Edge::vertical(1, 3)),
+            [Dot(1, 3), Dot(2, 3)]
+        );
+
+        // The same turn as the grids the rules used to keep.
+        let mut grids = [
+            Grid::from_vec(values[..9].to_vec(), 3),
+            Grid::from_vec(values[9..].to_vec(), 4),
+        ];
+        grids[0].rotate_right();
+        grids[1].rotate_right();
+        grids.rotate_right(1);
+        fences.rotate();
+        assert_eq!(fences.size(), (3, 2));
+        // Turned grids keep their values column by column.
+        let turned: Vec<_> = grids
+            .iter()
+            .flat_map(|g| g.iter_rows().flat_map(|r| r.copied()))
+            .collect();
+        assert_eq!(fences, Fences::from_vec(3, 2, turned));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Edge 1 2 0 is off the board")]
+    fn check_off_board() {
+        Fences::new(2, 3).edge_dots(Edge::vertical(2, 0));
     }
 }
@@ -0,0 +1,436 @@
+use anyhow::{bail, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use fences::{
+    batch::batch_solve,
+    board::print_board,
+    catalogue::write_rule_catalogue,
+    formats::{load_puzzle, parse_puzzle, write_puzzle, PuzzleFormat},
+    game,
+    generate::generate,
+    grade::grade,
+    pdf::{write_booklet, BookletOptions, BookletPuzzle},
+    rules::{BoardRule, RuleCheck},
+    save::save_path,
+    solver::{search, solve_with},
+    svg::SvgOptions,
+    tui::play_tui,
+    Board, FencesSolver, SaveGame,
+};
+use std::{io::Read, process::ExitCode};
+
+/// The puzzle is solved, or the solution is correct.
+const SOLVED: u8 = 0;
+/// The solver or the solution left edges undecided.
+const INCOMPLETE: u8 = 3;
+/// The puzzle or the solution breaks a rule of the game.
+const WRONG: u8 = 4;
+
+#[derive(Parser)]
+#[command(
+    version,
+    about = "Play, solve and convert fences (slitherlink) puzzles",
+    after_help = "Exit codes: 0 solved/valid, 1 error, 2 bad arguments, 3 incomplete, 4 wrong"
+)]
+struct Cli {
+    /// Rule file used by the solver, the built-in rules when missing
+    #[arg(long, global = true)]
+    rules: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum RenderFormat {
+    Text,
+    Svg,
+    Pdf,
+}
+
+#[derive(Subcommand)]
+enum RulesCommand {
+    /// Print every rule with its before and after diagram
+    List,
+    /// Check that every rule follows from the clues and fences it matches
+    Verify,
+    /// Write a markdown or html catalogue of the rules
+    Catalogue {
+        output: String,
+        /// Include every rotation of each rule
+        #[arg(long)]
+        rotations: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Play a puzzle in the terminal, resuming its save file when present
+    Play {
+        puzzle: String,
+        /// Save file, `<puzzle>.sol.txt` when missing
+        #[arg(long)]
+        save: Option<String>,
+        /// Type moves at a prompt instead of the full screen board
+        #[arg(long)]
+        repl: bool,
+    },
+    /// Solve a puzzle and print the solution or how far the solver got
+    Solve {
+        puzzle: String,
+        /// Guess undecided edges when the rules get stuck
+        #[arg(long)]
+        search: bool,
+        /// Print in this format instead of a drawing
+        #[arg(long)]
+        to: Option<PuzzleFormat>,
+    },
+    /// Solve every puzzle in files or directories and report how it went
+    Batch {
+        paths: Vec<String>,
+        /// Also write the report as json
+        #[arg(long)]
+        json: Option<String>,
+        /// Also write the report as csv
+        #[arg(long)]
+        csv: Option<String>,
+    },
+    /// Check that a file holds a complete and correct solution
+    Check {
+        /// Save game, `.sol.txt` or `cols#clues` header with a solution line
+        solution: String,
+        /// Puzzle the solution has to belong to
+        #[arg(long)]
+        puzzle: Option<String>,
+    },
+    /// Grade the difficulty of puzzles
+    Grade { puzzles: Vec<String> },
+    /// Generate puzzles with a unique solution
+    Generate {
+        #[arg(long, default_value_t = 5)]
+        rows: usize,
+        #[arg(long, default_value_t = 5)]
+        cols: usize,
+        /// Seed of the first puzzle, the time when missing
+        #[arg(long)]
+        seed: Option<u64>,
+        #[arg(long, default_value_t = 1)]
+        count: usize,
+        /// Only keep puzzles the rules solve without guessing
+        #[arg(long)]
+        rules_only: bool,
+        #[arg(long, default_value = "header")]
+        to: PuzzleFormat,
+    },
+    /// Render puzzles as text, svg or a pdf booklet
+    Render {
+        puzzles: Vec<String>,
+        #[arg(long, value_enum, default_value = "text")]
+        format: RenderFormat,
+        /// Output file, stdout when missing (required for pdf)
+        #[arg(short, long)]
+        output: Option<String>,
+        /// Render the solved board instead of the puzzle
+        #[arg(long)]
+        solve: bool,
+        #[arg(long, default_value_t = 40.)]
+        cell_size: f64,
+        #[arg(long)]
+        no_crosses: bool,
+        #[arg(long)]
+        no_color: bool,
+        /// Label pdf puzzles with their grade
+        #[arg(long)]
+        grade: bool,
+        #[arg(long, default_value_t = 1)]
+        per_page: usize,
+    },
+    /// List or verify the solver rules
+    Rules {
+        #[command(subcommand)]
+        command: RulesCommand,
+    },
+    /// Convert a puzzle from one format to another
+    Convert {
+        /// Puzzle file, `-` for stdin, or a puzzle url
+        input: String,
+        /// Format of the input, detected when missing
+        #[arg(long)]
+        from: Option<PuzzleFormat>,
+        /// One of header, grid, janko, url, drawing, save
+        #[arg(long, default_value = "header")]
+        to: PuzzleFormat,
+        /// Output file, stdout when missing
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+}
+
+/// Reads `input` as a file, stdin for `-`, or takes it literally when it
+/// looks like a puzzle (`5#...`) or a url.
+fn read_input(input: &str) -> Result<String> {
+    if input == "-" {
+        let mut s = String::new();
+        std::io::stdin().read_to_string(&mut s)?;
+        Ok(s)
+    } else if std::path::Path::new(input).exists() {
+        Ok(std::fs::read_to_string(input)?)
+    } else if input.contains('#') || input.contains("://") {
+        Ok(input.to_string())
+    } else {
+        bail!("`{input}`: no such file")
+    }
+}
+
+fn read_puzzle(input: &str) -> Result<Board> {
+    load_puzzle(&read_input(input)?)
+}
+
+fn write_output(output: Option<String>, out: &str) -> Result<()> {
+    match output {
+        Some(file) => std::fs::write(file, out)?,
+        None => print!("{out}"),
+    }
+    Ok(())
+}
+
+fn status(board: &Board) -> u8 {
+    match board.result() {
+        Some(true) => SOLVED,
+        Some(false) => WRONG,
+        None => INCOMPLETE,
+    }
+}
+
+fn run(cli: Cli) -> Result<u8> {
+    let base_rules = || match &cli.rules {
+        Some(file) => BoardRule::read_base_rules_from_yaml(file),
+        None => Ok(BoardRule::default_base_rules()),
+    };
+    let rules = || anyhow::Ok(BoardRule::with_rotations(&base_rules()?));
+    match cli.command {
+        Command::Play { puzzle, save, repl } => {
+            let sol_file = save.unwrap_or(save_path(&puzzle));
+            if sol_file == puzzle && !puzzle.ends_with(".sol.txt") {
+                bail!("`{puzzle}`: saving would overwrite the puzzle, pass --save");
+            }
+            let save = if std::path::Path::new(&sol_file).exists() {
+                SaveGame::load(&sol_file)?
+            } else {
+                SaveGame::new(&read_puzzle(&puzzle)?, Some(puzzle))
+            };
+            if repl {
+                game(save, &sol_file, &rules()?)?;
+            } else {
+                play_tui(save, &sol_file, &rules()?)?;
+            }
+            Ok(SOLVED)
+        }
+        Command::Solve {
+            puzzle,
+            search: guess,
+            to,
+        } => {
+            let rules = rules()?;
+            let mut board = read_puzzle(&puzzle)?;
+            let solved = solve_with(&mut board, &rules);
+            if let Err(c) = &solved {
+                eprintln!("The puzzle has no solution: {c}");
+            }
+            if guess && solved.is_ok() && board.result().is_none() {
+                let res = search(&board, &rules, 2, fences::grade::MAX_GUESSES);
+                match res.solutions.len() {
+                    1 if res.complete => board = res.solutions[0].clone(),
+                    0 if res.complete => eprintln!("The puzzle has no solution"),
+                    2.. => eprintln!("The puzzle has more than one solution"),
+                    _ => eprintln!("Gave up after {} guesses", res.nodes),
+                }
+            }
+            match to {
+                Some(format) => println!("{}", write_puzzle(&board, format)?),
+                None => print!("{board}"),
+            }
+            let total = board.fences_iter().count();
+            let decided = board.fences_iter().filter(|(_, f)| f.is_some()).count();
+            eprintln!("{decided}/{total} edges decided");
+            Ok(if solved.is_err() { WRONG } else { status(&board) })
+        }
+        Command::Batch { paths, json, csv } => {
+            if paths.is_empty() {
+                bail!("Give at least one puzzle file or directory")
+            }
+            let paths: Vec<_> = paths.iter().map(Into::into).collect();
+            let report = batch_solve(&paths, cli.rules.as_deref())?;
+            print!("{}", report.table());
+            if let Some(file) = json {
+                std::fs::write(file, report.to_json()?)?;
+            }
+            if let Some(file) = csv {
+                std::fs::write(file, report.to_csv())?;
+            }
+            Ok(SOLVED)
+        }
+        Command::Check { solution, puzzle } => {
+            let board = read_puzzle(&solution)?;
+            if let Some(puzzle) = puzzle {
+                if read_puzzle(&puzzle)?.tasks() != board.tasks() {
+                    bail!("`{solution}` is not a solution of `{puzzle}`")
+                }
+            }
+            let code = status(&board);
+            println!(
+                "{}",
+                match code {
+                    SOLVED => "The solution is correct",
+                    WRONG => "The solution is wrong",
+                    _ => "The solution is incomplete",
+                }
+            );
+            Ok(code)
+        }
+        Command::Grade { puzzles } => {
+            let rules = rules()?;
+            for p in puzzles {
+                let g = grade(&read_puzzle(&p)?, &rules);
+                println!(
+                    "{p}: {} ({} rounds, {} deductions, {} guesses)",
+                    g.difficulty, g.rounds, g.deductions, g.guesses
+                );
+            }
+            Ok(SOLVED)
+        }
+        Command::Generate {
+            rows,
+            cols,
+            seed,
+            count,
+            rules_only,
+            to,
+        } => {
+            if rows == 0 || cols == 0 {
+                bail!("A puzzle needs at least one row and one column")
+            }
+            let rules = rules()?;
+            let seed = seed.unwrap_or_else(fences::save::now);
+            for i in 0..count as u64 {
+                let board = generate(rows, cols, seed.wrapping_add(i), &rules, rules_only);
+                println!("{}", write_puzzle(&board, to)?);
+            }
+            Ok(SOLVED)
+        }
+        Command::Render {
+            puzzles,
+            format,
+            output,
+            solve,
+            cell_size,
+            no_crosses,
+            no_color,
+            grade: with_grade,
+            per_page,
+        } => {
+            let rules = rules()?;
+            let mut boards = vec![];
+            for p in &puzzles {
+                let mut board = read_puzzle(p)?;
+                if solve {
+                    if let Err(c) = solve_with(&mut board, &rules) {
+                        eprintln!("`{p}` has no solution: {c}");
+                    }
+                }
+                boards.push(board);
+            }
+            match format {
+                RenderFormat::Text => {
+                    let out: String = boards
+                        .iter()
+                        .map(|b| print_board(b.tasks(), b.fences(), !no_color) + "\n")
+                        .collect();
+                    write_output(output, &out)?
+                }
+                RenderFormat::Svg => {
+                    let opts = SvgOptions {
+                        cell_size,
+                        crosses: !no_crosses,
+                        color: !no_color,
+                    };
+                    let out: String = boards.iter().map(|b| b.to_svg(&opts)).collect();
+                    write_output(output, &out)?
+                }
+                RenderFormat::Pdf => {
+                    let Some(output) = output else {
+                        bail!("Rendering a pdf needs an --output file")
+                    };
+                    let entries: Vec<_> = puzzles
+                        .into_iter()
+                        .zip(boards)
+                        .map(|(title, board)| BookletPuzzle {
+                            difficulty: with_grade
+                                .then(|| grade(&board, &rules).difficulty.to_string()),
+                            title,
+                            board,
+                        })
+                        .collect();
+                    write_booklet(
+                        &output,
+                        &entries,
+                        &rules,
+                        &BookletOptions {
+                            per_page,
+                            ..Default::default()
+                        },
+                    )?
+                }
+            }
+            Ok(SOLVED)
+        }
+        Command::Rules { command } => {
+            let base = base_rules()?;
+            match command {
+                RulesCommand::List => {
+                    for (i, r) in base.iter().enumerate() {
+                        println!("Rule {i} ({}):\n{r}", r.variant);
+                    }
+                    Ok(SOLVED)
+                }
+                RulesCommand::Verify => {
+                    let mut code = SOLVED;
+                    for (i, r) in base.iter().enumerate() {
+                        let check = r.verify();
+                        if check != RuleCheck::Sound {
+                            code = WRONG;
+                            println!("Rule {i} ({}): {check:?}\n{r}", r.variant);
+                        }
+                    }
+                    if code == SOLVED {
+                        println!("All {} rules are sound", base.len());
+                    }
+                    Ok(code)
+                }
+                RulesCommand::Catalogue { output, rotations } => {
+                    write_rule_catalogue(&base, &output, rotations)?;
+                    Ok(SOLVED)
+                }
+            }
+        }
+        Command::Convert {
+            input,
+            from,
+            to,
+            output,
+        } => {
+            let s = read_input(&input)?;
+            let board = parse_puzzle(&s, from.unwrap_or(PuzzleFormat::detect(&s)))?;
+            write_output(output, &(write_puzzle(&board, to)? + "\n"))?;
+            Ok(SOLVED)
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(code) => ExitCode::from(code),
+        Err(e) => {
+            eprintln!("Error: {e:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
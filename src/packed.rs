@@ -0,0 +1,379 @@
+use crate::{
+    board::{print_board, write_ruled, Board, Move, MoveSource, Task, Tasks},
+    geom::{BoardGeom, Cell, Dot, Edge},
+    items::{edge_at, edge_id, EdgeId},
+    solver::{Changed, Conflict, FencesSolver, Snapshot},
+    Fence, Fences,
+};
+use grid::Grid;
+use std::{fmt, sync::Arc};
+
+const UNKNOWN: &Fence = &Fence(None);
+const LINE: &Fence = &Fence(Some(true));
+const CROSS: &Fence = &Fence(Some(false));
+
+/// A board keeping its edges as two bitsets, one with the decided edges and
+/// one with the lines, for searches that copy and scan boards a lot.
+///
+/// Edges are numbered as in [`Board::solution`]: the horizontal ones row by
+/// row, then the vertical ones. The edges around each cell and at each dot
+/// are kept as masks over the words they fall in, so counting them takes a
+/// popcount per word.
+#[derive(Debug, Clone)]
+pub struct PackedBoard {
+    rows: usize,
+    cols: usize,
+    tasks: Vec<Task>,
+    known: Vec<u64>,
+    on: Vec<u64>,
+    moves: Vec<Move>,
+    /// Masks of the edges around each cell, row by row.
+    cells: Arc<[Masks]>,
+    /// Masks of the edges at each dot, row by row.
+    dots: Arc<[Masks]>,
+}
+
+/// Edges as `(word, mask)` pairs, a pair for each of the at most four words
+/// they fall in. Unused pairs have an empty mask.
+type Masks = [(usize, u64); 4];
+
+fn masks(ids: impl IntoIterator<Item = usize>) -> Masks {
+    let mut ret = [(0, 0); 4];
+    let mut n = 0;
+    for id in ids {
+        let (w, m) = (id / 64, 1 << (id % 64));
+        match ret[..n].iter_mut().find(|x| x.0 == w) {
+            Some(x) => x.1 |= m,
+            None => {
+                ret[n] = (w, m);
+                n += 1;
+            }
+        }
+    }
+    ret
+}
+
+impl PackedBoard {
+    /// A board with the given clues and no fences decided.
+    pub fn new(tasks: &Tasks) -> Self {
+        let (rows, cols) = tasks.size();
+        let words = (2 * rows * cols + rows + cols).div_ceil(64);
+        let mut ret = Self {
+            rows,
+            cols,
+            tasks: tasks.iter_rows().flatten().copied().collect(),
+            known: vec![0; words],
+            on: vec![0; words],
+            moves: vec![],
+            cells: Arc::new([]),
+            dots: Arc::new([]),
+        };
+        let ids = |edges: &[Edge]| masks(edges.iter().map(|&e| ret.e2id(e)));
+        let cells: Vec<_> = (0..rows * cols)
+            .map(|i| ids(&ret.cell_edges(Cell(i / cols, i % cols))))
+            .collect();
+        let dots: Vec<_> = (0..(rows + 1) * (cols + 1))
+            .map(|i| ids(&ret.dot_edges(Dot(i / (cols + 1), i % (cols + 1)))))
+            .collect();
+        ret.cells = cells.into();
+        ret.dots = dots.into();
+        ret
+    }
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+    /// The fences as `y` and `n`, in the order of [`Board::solution`].
+    pub fn solution(&self) -> String {
+        (0..self.len())
+            .map(|id| if bit(&self.on, id) { 'y' } else { 'n' })
+            .collect()
+    }
+    /// The number of edges.
+    pub fn len(&self) -> usize {
+        2 * self.rows * self.cols + self.rows + self.cols
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// The number of lines on the board.
+    pub fn line_count(&self) -> usize {
+        self.on.iter().map(|w| w.count_ones() as usize).sum()
+    }
+    /// The number of decided edges.
+    pub fn decided_count(&self) -> usize {
+        self.known.iter().map(|w| w.count_ones() as usize).sum()
+    }
+    /// Lines around `cell`.
+    pub fn cell_lines(&self, cell: Cell) -> u32 {
+        self.cell_count(&self.on, cell)
+    }
+    /// Crosses around `cell`.
+    pub fn cell_crosses(&self, cell: Cell) -> u32 {
+        self.cell_count(&self.known, cell) - self.cell_lines(cell)
+    }
+    /// Lines at `dot`.
+    pub fn dot_lines(&self, dot: Dot) -> u32 {
+        self.dot_count(&self.on, dot)
+    }
+    /// Crosses at `dot`.
+    pub fn dot_crosses(&self, dot: Dot) -> u32 {
+        self.dot_count(&self.known, dot) - self.dot_lines(dot)
+    }
+    fn cell_count(&self, words: &[u64], Cell(r, c): Cell) -> u32 {
+        count(words, &self.cells[r * self.cols + c])
+    }
+    fn dot_count(&self, words: &[u64], Dot(r, c): Dot) -> u32 {
+        count(words, &self.dots[r * (self.cols + 1) + c])
+    }
+    #[inline]
+    fn e2id(&self, e: Edge) -> usize {
+        edge_id(self.size(), e).0
+    }
+    #[inline]
+    fn id2e(&self, id: usize) -> Edge {
+        edge_at(self.size(), EdgeId(id))
+    }
+    fn fence(&self, id: usize) -> &'static Fence {
+        match (bit(&self.known, id), bit(&self.on, id)) {
+            (false, _) => UNKNOWN,
+            (true, true) => LINE,
+            (true, false) => CROSS,
+        }
+    }
+    fn set(&mut self, id: usize, value: Option<bool>) {
+        let (w, m) = (id / 64, 1 << (id % 64));
+        self.known[w] &= !m;
+        self.on[w] &= !m;
+        if let Some(v) = value {
+            self.known[w] |= m;
+            if v {
+                self.on[w] |= m;
+            }
+        }
+    }
+}
+
+#[inline]
+fn bit(words: &[u64], id: usize) -> bool {
+    words[id / 64] >> (id % 64) & 1 == 1
+}
+
+/// The number of edges of `masks` set in `words`.
+#[inline]
+fn count(words: &[u64], masks: &Masks) -> u32 {
+    masks
+        .iter()
+        .map(|&(w, m)| (words[w] & m).count_ones())
+        .sum()
+}
+
+impl From<&Board> for PackedBoard {
+    /// Takes the fences and the moves of `board`.
+    fn from(board: &Board) -> Self {
+        let mut ret = Self::new(board.tasks());
+        for (e, f) in board.fences_iter() {
+            let id = ret.e2id(e);
+            ret.set(id, f.0);
+        }
+        ret.moves = board.moves().clone();
+        ret
+    }
+}
+
+impl core::str::FromStr for PackedBoard {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<Board>().map(|b| Self::from(&b))
+    }
+}
+
+impl fmt::Display for PackedBoard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fences = (0..self.len()).map(|id| *self.fence(id)).collect();
+        let board = print_board(
+            &Grid::from_vec(self.tasks.clone(), self.cols),
+            &Fences::from_vec(self.rows, self.cols, fences),
+            true,
+        );
+        write_ruled(f, &board, self.cols)
+    }
+}
+
+impl BoardGeom for PackedBoard {
+    fn size(&self) -> (usize, usize) {
+        (self.rows, self.cols)
+    }
+    /// Turns the board a quarter clockwise, the edges of its moves too.
+    fn rotate(&mut self) {
+        let mut tasks = Grid::from_vec(std::mem::take(&mut self.tasks), self.cols);
+        tasks.rotate_right();
+        let mut ret = Self::new(&tasks);
+        for id in 0..self.len() {
+            let to = ret.e2id(self.id2e(id).rotate(self.rows));
+            ret.set(to, self.fence(id).0);
+        }
+        ret.moves = std::mem::take(&mut self.moves)
+            .into_iter()
+            .map(|m| Move {
+                edge: m.edge.rotate(self.rows),
+                ..m
+            })
+            .collect();
+        *self = ret;
+    }
+}
+
+impl FencesSolver for PackedBoard {
+    fn set_solution(&mut self, solution: &str) {
+        for (id, v) in solution.chars().enumerate().take(self.len()) {
+            let f: Fence = v.try_into().unwrap();
+            self.set(id, f.0);
+        }
+        log::info!("set_solution\n{self}");
+    }
+    fn try_play(
+        &mut self,
+        edge: Edge,
+        value: bool,
+        source: MoveSource,
+    ) -> Result<Changed, Conflict> {
+        let id = self.e2id(edge);
+        if bit(&self.known, id) {
+            if bit(&self.on, id) == value {
+                return Ok(Changed::Unchanged);
+            }
+            return Err(Conflict {
+                edge,
+                value,
+                source,
+                by: self
+                    .moves
+                    .iter()
+                    .rfind(|m| m.edge == edge)
+                    .cloned()
+                    .map(Box::new),
+            });
+        }
+        self.set(id, Some(value));
+        log::trace!("{edge} {value} by {source}");
+        self.moves.push(Move {
+            edge,
+            value,
+            source,
+        });
+        Ok(Changed::Set)
+    }
+    fn snapshot(&self) -> Snapshot {
+        Snapshot(self.moves.len())
+    }
+    fn restore(&mut self, snapshot: Snapshot) {
+        debug_assert!(snapshot.0 <= self.moves.len(), "Restoring a lost snapshot");
+        while self.moves.len() > snapshot.0 {
+            let m = self.moves.pop().unwrap();
+            let id = self.e2id(m.edge);
+            self.set(id, None);
+        }
+    }
+    fn tasks_iter(&self) -> impl Iterator<Item = (Cell, &Task)> {
+        self.tasks
+            .iter()
+            .enumerate()
+            .map(|(i, t)| (Cell(i / self.cols, i % self.cols), t))
+    }
+    fn task(&self, Cell(r, c): Cell) -> &Task {
+        &self.tasks[r * self.cols + c]
+    }
+    fn edge(&self, e: Edge) -> &Fence {
+        self.fence(self.e2id(e))
+    }
+    fn fences_iter(&self) -> impl Iterator<Item = (Edge, &Fence)> {
+        (0..self.len()).map(|id| (self.id2e(id), self.fence(id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{formats::load_puzzle, solver::solve};
+    #[test]
+    fn check_packed_board() {
+        let puzzle = "5#         2  3331 0 1 3  3";
+        let mut board: Board = puzzle.parse().unwrap();
+        let mut packed: PackedBoard = puzzle.parse().unwrap();
+        solve(&mut board).unwrap();
+        solve(&mut packed).unwrap();
+        assert_eq!(packed.solution(), board.solution());
+        assert_eq!(packed.moves(), &board.moves()[..]);
+        assert!(board
+            .fences_iter()
+            .zip(packed.fences_iter())
+            .all(|(a, b)| a == b));
+        assert_eq!(packed.line_count(), board.solution().matches('y').count());
+
+        check_counts(&board, &packed);
+
+        // Edges spread over several words.
+        let s = std::fs::read_to_string("puzzle/example-15x15.txt").unwrap();
+        let mut board = load_puzzle(&s).unwrap();
+        solve(&mut board).unwrap();
+        check_counts(&board, &PackedBoard::from(&board));
+    }
+
+    fn check_counts(board: &Board, packed: &PackedBoard) {
+        for (cell, _) in board.tasks_iter() {
+            let around = board.cell_edges(cell).map(|e| *board.edge(e));
+            let count = |v| around.iter().filter(|f| f.0 == Some(v)).count() as u32;
+            assert_eq!(packed.cell_lines(cell), count(true));
+            assert_eq!(packed.cell_crosses(cell), count(false));
+        }
+        let (rows, cols) = board.size();
+        for dot in (0..=rows).flat_map(|r| (0..=cols).map(move |c| Dot(r, c))) {
+            let at: Vec<_> = board
+                .dot_edges(dot)
+                .iter()
+                .map(|&e| *board.edge(e))
+                .collect();
+            let count = |v| at.iter().filter(|f| f.0 == Some(v)).count() as u32;
+            assert_eq!(packed.dot_lines(dot), count(true));
+            assert_eq!(packed.dot_crosses(dot), count(false));
+        }
+    }
+
+    #[test]
+    fn check_packed_snapshot() {
+        let mut packed: PackedBoard = "2#33".parse().unwrap();
+        let snapshot = packed.snapshot();
+        packed.play(Edge::vertical(0, 2), true, MoveSource::Search);
+        assert_eq!(packed.cell_lines(Cell(0, 1)), 1);
+        assert!(packed
+            .try_play(Edge::vertical(0, 2), false, MoveSource::Player)
+            .is_err());
+        packed.restore(snapshot);
+        assert_eq!(packed.decided_count(), 0);
+        assert!(packed.edge(Edge::vertical(0, 2)).is_none());
+    }
+
+    #[test]
+    fn check_packed_rotate() {
+        let puzzle = "3#2 1 3 ";
+        let mut board: Board = puzzle.parse().unwrap();
+        solve(&mut board).unwrap();
+        let mut packed = PackedBoard::from(&board);
+        let mut fences = board.fences().clone();
+        let mut tasks = board.tasks().clone();
+        packed.rotate();
+        fences.rotate();
+        tasks.rotate_right();
+        assert_eq!(packed.size(), (3, 2));
+        assert!(packed.tasks_iter().all(|(c, t)| t == &tasks[c.into()]));
+        assert!(packed.fences_iter().all(|(e, f)| f == &fences[e]));
+        assert!(packed
+            .moves()
+            .iter()
+            .all(|m| fences[m.edge].0 == Some(m.value)));
+        // Four turns bring it back.
+        (0..3).for_each(|_| packed.rotate());
+        assert_eq!(packed.solution(), board.solution());
+        assert_eq!(packed.moves(), &board.moves()[..]);
+    }
+}
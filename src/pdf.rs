@@ -0,0 +1,270 @@
+use crate::{
+    board::Tasks, grade::MAX_GUESSES, rules::BoardRule, solver, Board, Direction, Edge, Fences,
+};
+use pdf_writer::{Content, Name, Pdf, Rect, Ref, Str};
+
+const A4: (f32, f32) = (595., 842.);
+const MARGIN: f32 = 40.;
+const HEADER: f32 = 30.;
+const LABEL: f32 = 18.;
+const FONT: Name = Name(b"F1");
+/// Width of a digit in Helvetica, in em.
+const DIGIT_WIDTH: f32 = 0.556;
+
+#[derive(Debug, Clone)]
+pub struct BookletPuzzle {
+    pub title: String,
+    pub difficulty: Option<String>,
+    pub board: Board,
+}
+
+#[derive(Debug, Clone)]
+pub struct BookletOptions {
+    pub title: String,
+    pub per_page: usize,
+    pub answers_per_page: usize,
+}
+
+impl Default for BookletOptions {
+    fn default() -> Self {
+        Self {
+            title: "Fences".to_string(),
+            per_page: 1,
+            answers_per_page: 4,
+        }
+    }
+}
+
+/// Standard fonts only cover latin-1, keep the text printable.
+fn printable(s: &str) -> Vec<u8> {
+    s.chars()
+        .map(|c| if c.is_ascii() { c as u8 } else { b'?' })
+        .collect()
+}
+
+fn text(content: &mut Content, x: f32, y: f32, size: f32, s: &str) {
+    content
+        .begin_text()
+        .set_font(FONT, size)
+        .next_line(x, y)
+        .show(Str(&printable(s)))
+        .end_text();
+}
+
+/// Draws the clues and the drawn fences of a board into the box whose top
+/// left corner is at `(x, y)`.
+fn draw_board(
+    content: &mut Content,
+    task: &Tasks,
+    fences: Option<&Fences>,
+    pos: (f32, f32, f32, f32),
+) {
+    let (x, y, w, h) = pos;
+    let (rows, cols) = task.size();
+    let cs = (w / cols as f32).min(h / rows as f32);
+    let x = x + (w - cs * cols as f32) / 2.;
+    let dot = |row: usize, col: usize| (x + col as f32 * cs, y - row as f32 * cs);
+
+    if let Some(fences) = fences {
+        content.set_line_width(cs / 12.);
+        for (Edge(dir, row, col), fence) in fences.iter() {
+            if fence.is_some_and(|x| x) {
+                let (x1, y1) = dot(row, col);
+                let (x2, y2) = if dir == Direction::Horizontal {
+                    dot(row, col + 1)
+                } else {
+                    dot(row + 1, col)
+                };
+                content.move_to(x1, y1).line_to(x2, y2);
+            }
+        }
+        content.stroke();
+    }
+
+    let d = (cs / 10.).max(1.);
+    for row in 0..=rows {
+        for col in 0..=cols {
+            let (x, y) = dot(row, col);
+            content.rect(x - d / 2., y - d / 2., d, d);
+        }
+    }
+    content.fill_nonzero();
+
+    let size = cs * 0.6;
+    for ((row, col), t) in task.indexed_iter() {
+        if let Some(t) = t {
+            let (x, y) = dot(row, col);
+            text(
+                content,
+                x + (cs - DIGIT_WIDTH * size) / 2.,
+                y - cs / 2. - size * 0.35,
+                size,
+                &t.to_string(),
+            );
+        }
+    }
+}
+
+/// Rows and columns of the grid the puzzles of a page are laid out on.
+fn layout(per_page: usize) -> (usize, usize) {
+    let rows = (per_page as f32).sqrt().ceil() as usize;
+    (rows, per_page.div_ceil(rows))
+}
+
+fn page(heading: &str, entries: &[(String, &Tasks, Option<&Fences>)], per_page: usize) -> Vec<u8> {
+    let mut content = Content::new();
+    text(&mut content, MARGIN, A4.1 - MARGIN - 16., 16., heading);
+
+    let (rows, cols) = layout(per_page);
+    let w = (A4.0 - 2. * MARGIN) / cols as f32;
+    let h = (A4.1 - 2. * MARGIN - HEADER) / rows as f32;
+    for (i, (label, task, fences)) in entries.iter().enumerate() {
+        let x = MARGIN + (i % cols) as f32 * w;
+        let y = A4.1 - MARGIN - HEADER - (i / cols) as f32 * h;
+        text(&mut content, x + 4., y - 12., 11., label);
+        let pad = 10.;
+        draw_board(
+            &mut content,
+            task,
+            *fences,
+            (x + pad, y - LABEL - pad, w - 2. * pad, h - LABEL - 2. * pad),
+        );
+    }
+    content.finish()
+}
+
+/// Renders the puzzles, `per_page` on each page, followed by an answer key
+/// with the unique solution of each puzzle found with `rules`. Puzzles
+/// without one are marked as having no answer.
+pub fn booklet_pdf(
+    puzzles: &[BookletPuzzle],
+    rules: &[BoardRule],
+    opts: &BookletOptions,
+) -> Vec<u8> {
+    let per_page = opts.per_page.max(1);
+    let answers_per_page = opts.answers_per_page.max(1);
+    let labels: Vec<_> = puzzles
+        .iter()
+        .enumerate()
+        .map(|(i, p)| match &p.difficulty {
+            Some(d) => format!("{}. {} ({d})", i + 1, p.title),
+            None => format!("{}. {}", i + 1, p.title),
+        })
+        .collect();
+    let answers: Vec<Option<Board>> = puzzles
+        .iter()
+        .map(|p| {
+            let res = solver::search(&p.board, rules, 2, MAX_GUESSES);
+            match res.solutions.len() {
+                1 if res.complete => return res.solutions.into_iter().next(),
+                0 if res.complete => log::warn!("No answer for {}", p.title),
+                2.. => log::warn!("More than one answer for {}", p.title),
+                _ => log::warn!("Gave up on {} after {} guesses", p.title, res.nodes),
+            }
+            None
+        })
+        .collect();
+
+    let mut streams = vec![];
+    let chunks: Vec<_> = puzzles.chunks(per_page).collect();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let entries: Vec<_> = chunk
+            .iter()
+            .enumerate()
+            .map(|(j, p)| (labels[i * per_page + j].clone(), p.board.tasks(), None))
+            .collect();
+        streams.push(page(
+            &format!("{} - page {}/{}", opts.title, i + 1, chunks.len()),
+            &entries,
+            per_page,
+        ));
+    }
+    for (i, chunk) in answers.chunks(answers_per_page).enumerate() {
+        let entries: Vec<_> = chunk
+            .iter()
+            .enumerate()
+            .map(|(j, b)| {
+                let k = i * answers_per_page + j;
+                match b {
+                    Some(b) => (labels[k].clone(), b.tasks(), Some(b.fences())),
+                    None => (
+                        format!("{} - no answer", labels[k]),
+                        puzzles[k].board.tasks(),
+                        None,
+                    ),
+                }
+            })
+            .collect();
+        streams.push(page(
+            &format!("{} - answers", opts.title),
+            &entries,
+            answers_per_page,
+        ));
+    }
+
+    let mut pdf = Pdf::new();
+    let catalog_id = Ref::new(1);
+    let page_tree_id = Ref::new(2);
+    let font_id = Ref::new(3);
+    let page_ids: Vec<_> = (0..streams.len())
+        .map(|i| Ref::new(4 + 2 * i as i32))
+        .collect();
+    pdf.catalog(catalog_id).pages(page_tree_id);
+    pdf.pages(page_tree_id)
+        .kids(page_ids.iter().copied())
+        .count(page_ids.len() as i32);
+    pdf.type1_font(font_id).base_font(Name(b"Helvetica"));
+    for (id, stream) in page_ids.iter().zip(streams.iter()) {
+        let content_id = Ref::new(id.get() + 1);
+        let mut page = pdf.page(*id);
+        page.media_box(Rect::new(0., 0., A4.0, A4.1))
+            .parent(page_tree_id)
+            .contents(content_id);
+        page.resources().fonts().pair(FONT, font_id);
+        drop(page);
+        pdf.stream(content_id, stream);
+    }
+    pdf.finish()
+}
+
+pub fn write_booklet(
+    out: &str,
+    puzzles: &[BookletPuzzle],
+    rules: &[BoardRule],
+    opts: &BookletOptions,
+) -> anyhow::Result<()> {
+    std::fs::write(out, booklet_pdf(puzzles, rules, opts))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn check_booklet_pdf() {
+        let puzzles: Vec<_> = ["2#32  ", "3#4  ", "2#33"]
+            .iter()
+            .map(|s| BookletPuzzle {
+                title: s.to_string(),
+                difficulty: Some("easy".to_string()),
+                board: s.parse().unwrap(),
+            })
+            .collect();
+        let rules = BoardRule::default_rules();
+        let pdf = booklet_pdf(
+            &puzzles,
+            &rules,
+            &BookletOptions {
+                per_page: 2,
+                ..Default::default()
+            },
+        );
+        let pdf = String::from_utf8_lossy(&pdf);
+        assert!(pdf.starts_with("%PDF"));
+        assert!(pdf.contains("/Count 3"));
+        assert_eq!(pdf.matches("/Type /Page\n").count(), 3);
+        // The bottom row of `2#32  ` has no clues, it has more than one answer.
+        assert_eq!(pdf.matches("- no answer").count(), 1);
+        assert!(pdf.contains("1. 2#32   (easy) - no answer"));
+    }
+}
@@ -1,14 +1,18 @@
 use crate::{
-    add_idx,
-    board::{print_board, Fences, Tasks},
-    sub_idx, Fence,
+    board::{print_board, MoveSource, Tasks},
+    geom::{BoardGeom, Cell, Dot, Edge},
+    sub_idx, Fence, Fences,
 };
+use anyhow::Context;
 use grid::Grid;
 use serde::Deserialize;
 use serde_yaml;
 use std::collections::HashSet;
 
-use super::FencesSolver;
+use super::{
+    solver::{Conflict, Idx, RULES},
+    FencesSolver,
+};
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub enum TaskType {
@@ -39,6 +43,33 @@ impl TaskType {
     }
 }
 
+impl core::fmt::Display for TaskType {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            TaskType::Corner(x) => write!(
+                f,
+                "{} corner",
+                ["top-left", "top-right", "bottom-right", "bottom-left"][x % 4]
+            ),
+            TaskType::Edge(x) => write!(f, "{} edge", ["top", "right", "bottom", "left"][x % 4]),
+            TaskType::None => write!(f, "anywhere"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleCheck {
+    /// Every arrangement matching the rule agrees with its solution.
+    Sound,
+    /// No arrangement of the edges matches the rule.
+    NeverApplies,
+    /// Some arrangement matching the rule disagrees with its solution on
+    /// these edges, or the rule needs more than local reasoning.
+    Unsound(Vec<Edge>),
+    /// Too many undecided edges to try them all.
+    TooLarge,
+}
+
 #[derive(Debug, Clone)]
 pub struct BoardRule {
     pub task: Tasks,
@@ -52,6 +83,7 @@ impl<'de> Deserialize<'de> for BoardRule {
     where
         D: serde::Deserializer<'de>,
     {
+        use serde::de::Error;
         #[derive(Deserialize)]
         struct Helper {
             task: String,
@@ -72,7 +104,12 @@ impl<'de> Deserialize<'de> for BoardRule {
             edge,
         } = helper;
 
-        let cols = task.lines().last().unwrap().chars().count();
+        let cols = task.lines().last().map_or(0, |l| l.chars().count());
+        if cols == 0 || task.lines().any(|l| l.chars().count() != cols) {
+            return Err(D::Error::custom(
+                "the task of a rule needs rows of the same length",
+            ));
+        }
         let task: Tasks = Grid::from_vec(
             task.replace('\n', "")
                 .chars()
@@ -80,18 +117,20 @@ impl<'de> Deserialize<'de> for BoardRule {
                 .collect(),
             cols,
         );
-        let size = task.size();
-        let boundary = (size.0 + 1) * size.1;
-        let fences: Vec<Fence> = fences.chars().filter_map(|c| c.try_into().ok()).collect();
-        let fences: Fences = [
-            Grid::from_vec(fences[0..boundary].to_vec(), task.cols()),
-            Grid::from_vec(fences[boundary..].to_vec(), task.cols() + 1),
-        ];
-        let solution: Vec<Fence> = solution.chars().filter_map(|c| c.try_into().ok()).collect();
-        let solution: Fences = [
-            Grid::from_vec(solution[0..boundary].to_vec(), task.cols()),
-            Grid::from_vec(solution[boundary..].to_vec(), task.cols() + 1),
-        ];
+        let (rows, cols) = task.size();
+        let edges = |s: String, what: &str| -> Result<Fences, D::Error> {
+            let fences: Vec<Fence> = s.chars().filter_map(|c| c.try_into().ok()).collect();
+            if fences.len() != 2 * rows * cols + rows + cols {
+                return Err(D::Error::custom(format!(
+                    "the {what} of a {rows}x{cols} rule needs {} edges, got {}",
+                    2 * rows * cols + rows + cols,
+                    fences.len()
+                )));
+            }
+            Ok(Fences::from_vec(rows, cols, fences))
+        };
+        let fences = edges(fences, "fences")?;
+        let solution = edges(solution, "solution")?;
         Ok(Self {
             task,
             fences,
@@ -111,15 +150,13 @@ impl BoardRule {
         } = self;
         [
             task.iter().map(|x| x.map_or(' ', char::from)).collect(),
-            fences[0].iter().map(|&x| char::from(x)).collect(),
-            fences[1].iter().map(|&x| char::from(x)).collect(),
-            solution[0].iter().map(|&x| char::from(x)).collect(),
-            solution[1].iter().map(|&x| char::from(x)).collect(),
+            fences.iter().map(|(_, &x)| char::from(x)).collect(),
+            solution.iter().map(|(_, &x)| char::from(x)).collect(),
             format!("{variant:?}"),
         ]
         .join("|")
     }
-    pub(crate) fn get_rotations(&self) -> Vec<Self> {
+    pub fn get_rotations(&self) -> Vec<Self> {
         let Self {
             task,
             fences,
@@ -128,20 +165,16 @@ impl BoardRule {
         } = self;
         let mut ret: Vec<Self> = vec![Self {
             task: task.clone(),
-            fences: [fences[0].clone(), fences[1].clone()],
-            solution: [solution[0].clone(), solution[1].clone()],
+            fences: fences.clone(),
+            solution: solution.clone(),
             variant: variant.clone(),
         }];
         let mut set = HashSet::from([self.to_hash()]);
         for _ in 1..4 {
             let mut rot = ret.last().unwrap().clone();
             rot.task.rotate_right();
-            rot.fences[1].rotate_right();
-            rot.fences[0].rotate_right();
-            rot.fences.rotate_right(1);
-            rot.solution[1].rotate_right();
-            rot.solution[0].rotate_right();
-            rot.solution.rotate_right(1);
+            rot.fences.rotate();
+            rot.solution.rotate();
             match &mut rot.variant {
                 TaskType::Corner(v) => *v += 1,
                 TaskType::Edge(v) => *v += 1,
@@ -155,10 +188,113 @@ impl BoardRule {
         }
         ret
     }
-    pub fn apply_at(&self, board: &mut impl FencesSolver, idx: (usize, usize)) -> Option<bool> {
+    /// Checks the rule against every arrangement of its window's edges that
+    /// matches `fences`, keeps clue counts and doesn't give a dot more than
+    /// two lines or a dead end. Sides of the window on the board border (for
+    /// `corner` and `edge` rules) have no edges beyond them.
+    pub fn verify(&self) -> RuleCheck {
+        let (rows, cols) = self.task.size();
+        let side = |s: usize| match self.variant {
+            TaskType::Corner(x) => x % 4 == s || (x + 3) % 4 == s,
+            TaskType::Edge(x) => x % 4 == s,
+            TaskType::None => false,
+        };
+        let (top, right, bottom, left) = (side(0), side(1), side(2), side(3));
+        let edges: Vec<_> = self
+            .fences
+            .iter()
+            .map(|(e, &f)| (e, f, self.solution[e]))
+            .collect();
+        let free: Vec<_> = (0..edges.len()).filter(|&i| edges[i].1.is_none()).collect();
+        if free.len() > 20 {
+            return RuleCheck::TooLarge;
+        }
+        let id = |e: Edge| self.fences.id(e).0;
+
+        let mut matched = false;
+        let mut unsound = vec![false; edges.len()];
+        let mut value: Vec<bool> = edges.iter().map(|e| e.1.is_some_and(|x| x)).collect();
+        for bits in 0u32..1 << free.len() {
+            for (j, &i) in free.iter().enumerate() {
+                value[i] = bits >> j & 1 == 1;
+            }
+            let clues_ok = self.task.indexed_iter().all(|(idx, t)| {
+                t.is_none_or(|t| {
+                    Cell::from(idx)
+                        .edges()
+                        .iter()
+                        .filter(|&&e| value[id(e)])
+                        .count()
+                        == t as usize
+                })
+            });
+            let dots_ok = (0..=rows).all(|r| {
+                (0..=cols).all(|c| {
+                    let mut lines = 0;
+                    let mut outside = 0;
+                    let borders = [right, bottom, left, top];
+                    for (e, border) in Dot(r, c).edges().into_iter().zip(borders) {
+                        match e.filter(|&e| self.fences.has_edge(e)) {
+                            Some(e) => lines += value[id(e)] as usize,
+                            None if !border => outside += 1,
+                            None => (),
+                        }
+                    }
+                    lines <= 2 && (lines != 1 || outside > 0)
+                })
+            });
+            // A loop closed within the window could only be the whole
+            // solution, which the solver never expects a rule to find.
+            let mut parent: Vec<usize> = (0..(rows + 1) * (cols + 1)).collect();
+            fn root(parent: &mut [usize], mut i: usize) -> usize {
+                while parent[i] != i {
+                    parent[i] = parent[parent[i]];
+                    i = parent[i];
+                }
+                i
+            }
+            let no_loop = edges.iter().enumerate().all(|(i, (e, ..))| {
+                if !value[i] {
+                    return true;
+                }
+                let [Dot(r, c), Dot(r1, c1)] = e.dots();
+                let a = root(&mut parent, r1 * (cols + 1) + c1);
+                let b = root(&mut parent, r * (cols + 1) + c);
+                parent[a] = b;
+                a != b
+            });
+            if clues_ok && dots_ok && no_loop {
+                matched = true;
+                for (i, e) in edges.iter().enumerate() {
+                    unsound[i] |= e.2.is_some_and(|x| x != value[i]);
+                }
+            }
+        }
+        if !matched {
+            return RuleCheck::NeverApplies;
+        }
+        let unsound: Vec<_> = (0..edges.len())
+            .filter(|&i| unsound[i])
+            .map(|i| edges[i].0)
+            .collect();
+        if unsound.is_empty() {
+            RuleCheck::Sound
+        } else {
+            RuleCheck::Unsound(unsound)
+        }
+    }
+    /// Applies the rule with its top left corner at `at`, recording the
+    /// moves as made by rule `id`. Fails when the rule contradicts an edge.
+    pub fn apply_at(
+        &self,
+        board: &mut impl FencesSolver,
+        at: Cell,
+        id: usize,
+    ) -> Result<Option<bool>, Conflict> {
+        let idx: Idx = at.into();
         let size = self.task.size();
         if board.cols() < size.1 || board.rows() < size.0 {
-            return None;
+            return Ok(None);
         }
         let bounds = sub_idx(board.size(), size);
         if idx.0 > bounds.0
@@ -171,62 +307,72 @@ impl BoardRule {
                 _ => false,
             }
         {
-            return None;
+            return Ok(None);
         }
         let task_match = self
             .task
             .indexed_iter()
             .filter(|x| x.1.is_some())
-            .all(|(i, x)| x == board.task(add_idx(i, idx)))
-            && [0usize, 1].iter().any(|&dir| {
-                self.solution[dir]
-                    .indexed_iter()
-                    .filter_map(|x| x.1.map(|_| x.0))
-                    .any(|i| board.edge(dir, add_idx(i, idx)).is_none())
-            });
+            .all(|(i, x)| x == board.task(Cell::from(i).offset(idx)))
+            && self
+                .solution
+                .iter()
+                .filter(|x| x.1.is_some())
+                .any(|(e, _)| board.edge(e.offset(idx)).is_none());
         if !task_match {
-            return None;
+            return Ok(None);
         }
 
         if task_match
-            && [0usize, 1].iter().all(|&dir| {
-                self.fences[dir]
-                    .indexed_iter()
-                    .filter(|x| x.1.is_some())
-                    .all(|(i, x)| x == board.edge(dir, add_idx(i, idx)))
-            })
+            && self
+                .fences
+                .iter()
+                .filter(|x| x.1.is_some())
+                .all(|(e, x)| x == board.edge(e.offset(idx)))
         {
             log::trace!(
-                "match at idx: {idx:?} size: {size:?} bounds: {bounds:?} {:?}",
+                "match at {at} size: {size:?} bounds: {bounds:?} {:?}",
                 self.task
                     .indexed_iter()
-                    .map(|(i, _)| board.task(add_idx(i, idx)).clone())
+                    .map(|(i, _)| *board.task(Cell::from(i).offset(idx)))
                     .collect::<Vec<_>>()
             );
-            for dir in [0, 1] {
-                self.solution[dir]
-                    .indexed_iter()
-                    .filter_map(|x| x.1.map(|v| (x.0, v)))
-                    .for_each(|(i, x)| board.play(dir, add_idx(i, idx), x, format!("{self}")))
+            for (e, x) in self.solution.iter().filter_map(|x| x.1.map(|v| (x.0, v))) {
+                board.try_play(e.offset(idx), x, MoveSource::Rule { id, at })?;
             }
-            Some(false)
+            Ok(Some(false))
         } else {
-            Some(true)
+            Ok(Some(true))
         }
     }
-    pub fn read_rules_from_yaml(file: &str) -> Vec<Self> {
-        let f = std::fs::File::open(file).expect("Couldn't open file");
-        let rules: Vec<BoardRule> = serde_yaml::from_reader(f).expect("Couldn't obtain rules");
-        rules.iter().flat_map(|x| x.get_rotations()).collect()
+    /// Rules as written in `file`, without their rotations.
+    pub fn read_base_rules_from_yaml(file: &str) -> anyhow::Result<Vec<Self>> {
+        let f = std::fs::File::open(file).with_context(|| format!("Couldn't open `{file}`"))?;
+        serde_yaml::from_reader(f).with_context(|| format!("Couldn't obtain rules from `{file}`"))
+    }
+    pub fn read_rules_from_yaml(file: &str) -> anyhow::Result<Vec<Self>> {
+        let base = Self::read_base_rules_from_yaml(file)?;
+        Ok(Self::with_rotations(&base))
+    }
+    /// The built-in rules of [`RULES`], without their rotations.
+    pub fn default_base_rules() -> Vec<Self> {
+        serde_yaml::from_str(RULES).expect("Couldn't obtain the built-in rules")
+    }
+    pub fn default_rules() -> Vec<Self> {
+        Self::with_rotations(&Self::default_base_rules())
+    }
+    /// Every rotation of each rule in `base`.
+    pub fn with_rotations(base: &[Self]) -> Vec<Self> {
+        base.iter().flat_map(|x| x.get_rotations()).collect()
     }
 }
 
 impl core::fmt::Display for BoardRule {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         if let TaskType::Corner(x) = self.variant {
-            write!(f, "corner: {x}\n")?;
+            writeln!(f, "corner: {x}")?;
         } else if let TaskType::Edge(x) = self.variant {
-            write!(f, "edge: {x}\n")?;
+            writeln!(f, "edge: {x}")?;
         }
         let from = print_board(&self.task, &self.fences, false)
             .lines()
@@ -236,3 +382,39 @@ impl core::fmt::Display for BoardRule {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn check_rule_verify() {
+        let rule = |task: &str, fences: &str, solution: &str, corner: bool| -> BoardRule {
+            serde_yaml::from_str(&format!(
+                "task: '{task}'\nfences: '{fences}'\nsolution: '{solution}'\ncorner: {corner}"
+            ))
+            .unwrap()
+        };
+        assert_eq!(rule("0", "....", "xxxx", false).verify(), RuleCheck::Sound);
+        assert_eq!(rule("1", "....", "x.x.", true).verify(), RuleCheck::Sound);
+        assert!(matches!(
+            rule("1", "....", "x.x.", false).verify(),
+            RuleCheck::Unsound(_)
+        ));
+        assert_eq!(rule("0", "-...", "xxxx", false).verify(), RuleCheck::NeverApplies);
+        for r in BoardRule::default_base_rules() {
+            assert_eq!(r.verify(), RuleCheck::Sound, "\n{r}");
+        }
+        let parse = |task: &str, fences: &str, solution: &str| {
+            serde_yaml::from_str::<BoardRule>(&format!(
+                "task: '{task}'\nfences: '{fences}'\nsolution: '{solution}'"
+            ))
+        };
+        assert!(parse("0", "....", "xxxx").is_ok());
+        assert!(parse("0", "...", "xxxx").is_err());
+        assert!(parse("0", "....", "xxxxx").is_err());
+        assert!(parse("", "", "").is_err());
+        assert!(parse("0\n00", "..........", "..........").is_err());
+        assert!(BoardRule::read_rules_from_yaml("nope.yml").is_err());
+        assert!(BoardRule::read_rules_from_yaml("Cargo.toml").is_err());
+    }
+}
@@ -0,0 +1,388 @@
+use crate::{board::MoveSource, Board, Direction, Edge, FencesSolver};
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Version of the saves written. Version 2 added moves that clear an edge,
+/// version 3 keeps checkpoints as nodes of the undo tree instead of board
+/// moves. Older saves are read as they are.
+pub const SAVE_VERSION: u32 = 3;
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Save file of `puzzle`: `puzzle` itself when it is a `.sol.txt`, or
+/// `puzzle` with a known extension swapped for `.sol.txt`.
+pub fn save_path(puzzle: &str) -> String {
+    if puzzle.ends_with(".sol.txt") {
+        return puzzle.to_string();
+    }
+    let stem = [".txt", ".grid", ".janko"]
+        .iter()
+        .find_map(|ext| puzzle.strip_suffix(ext))
+        .unwrap_or(puzzle);
+    format!("{stem}.sol.txt")
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerMove {
+    pub direction: Direction,
+    pub row: usize,
+    pub col: usize,
+    pub value: bool,
+    /// Erases the edge instead, `value` being what it held.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub clear: bool,
+    /// Unix time of the move, `0` when unknown.
+    #[serde(default)]
+    pub time: u64,
+}
+
+impl PlayerMove {
+    /// Parses the `0 3 2 y` notation used by the game prompt and old saves,
+    /// `.` in place of the value clearing the edge.
+    pub fn parse(s: &str) -> Result<Self> {
+        let mut m = s.split_whitespace();
+        let mut next = || m.next().ok_or(anyhow::anyhow!("Incomplete move `{s}`"));
+        let direction: usize = next()?.parse()?;
+        let row = next()?.parse()?;
+        let col = next()?.parse()?;
+        let (value, clear) = match next()? {
+            "y" | "-" => (true, false),
+            "n" | "x" => (false, false),
+            "." => (false, true),
+            x => bail!("Invalid value `{x}` in move `{s}`"),
+        };
+        let Ok(direction) = direction.try_into() else {
+            bail!("Invalid direction in move `{s}`")
+        };
+        Ok(Self {
+            direction,
+            row,
+            col,
+            value,
+            clear,
+            time: 0,
+        })
+    }
+}
+
+impl std::fmt::Display for PlayerMove {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}",
+            self.direction,
+            self.row,
+            self.col,
+            match (self.clear, self.value) {
+                (true, _) => '.',
+                (false, true) => 'y',
+                (false, false) => 'n',
+            }
+        )
+    }
+}
+
+impl PlayerMove {
+    pub fn edge(&self) -> Edge {
+        Edge(self.direction, self.row, self.col)
+    }
+    /// Whether both moves put the same value on the same edge.
+    pub fn same(&self, other: &PlayerMove) -> bool {
+        (self.direction, self.row, self.col, self.value, self.clear)
+            == (other.direction, other.row, other.col, other.value, other.clear)
+    }
+}
+
+/// How much the game plays for the player after each of their moves.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Assist {
+    None,
+    /// Crosses around 0s and completed dots.
+    Trivial,
+    /// Every rule, until nothing follows.
+    #[default]
+    Rules,
+    /// The rules, then the rest of the solution when it is unique. The
+    /// solver searches for it once per game.
+    Search,
+}
+
+impl Assist {
+    pub const ALL: [Assist; 4] = [Assist::None, Assist::Trivial, Assist::Rules, Assist::Search];
+
+    /// The next level, wrapping around to none.
+    pub fn next(self) -> Self {
+        Self::ALL[(self as usize + 1) % Self::ALL.len()]
+    }
+}
+
+impl std::fmt::Display for Assist {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", format!("{self:?}").to_lowercase())
+    }
+}
+
+impl std::str::FromStr for Assist {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|a| a.to_string() == s)
+            .ok_or_else(|| {
+                anyhow::anyhow!("Unknown assist level `{s}`, use none, trivial, rules or search")
+            })
+    }
+}
+
+/// A player move in the undo tree, following the move at `parent`, or the
+/// start of the game when there is none.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryNode {
+    pub parent: Option<usize>,
+    #[serde(flatten)]
+    pub player_move: PlayerMove,
+}
+
+/// A game in progress, stored as yaml.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SaveGame {
+    pub version: u32,
+    /// The puzzle in `cols#clues` notation.
+    pub puzzle: String,
+    /// File the puzzle was loaded from.
+    #[serde(default)]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub created: u64,
+    #[serde(default)]
+    pub saved: u64,
+    /// Seconds spent playing, over all sessions.
+    #[serde(default)]
+    pub elapsed: u64,
+    #[serde(default)]
+    pub completed: bool,
+    #[serde(default)]
+    pub assist: Assist,
+    /// The player moves leading to `current`.
+    #[serde(default)]
+    pub moves: Vec<PlayerMove>,
+    /// Every move the player tried, undone ones included.
+    #[serde(default)]
+    pub history: Vec<HistoryNode>,
+    /// Node of `history` the game is at, `None` before the first move.
+    #[serde(default)]
+    pub current: Option<usize>,
+    /// Nodes of `history` marked to come back to, the last one on top,
+    /// `None` for the start. Version 1 saves counted the board moves
+    /// instead, [`GameSession`](crate::GameSession) maps those onto nodes.
+    #[serde(default)]
+    pub checkpoints: Vec<Option<usize>>,
+    /// The solution in [`Board::solution`] notation, once known: the one
+    /// the game was completed with, or the unique one the solver found.
+    #[serde(default)]
+    pub solution: Option<String>,
+}
+
+impl SaveGame {
+    pub fn new(board: &Board, source: Option<String>) -> Self {
+        Self {
+            version: SAVE_VERSION,
+            puzzle: board.puzzle(),
+            source,
+            created: now(),
+            saved: 0,
+            elapsed: 0,
+            completed: false,
+            assist: Assist::default(),
+            moves: vec![],
+            history: vec![],
+            current: None,
+            checkpoints: vec![],
+            solution: None,
+        }
+    }
+
+    /// Reads a save, an old `.sol.txt` (header, move lines and an optional
+    /// solution) or a bare puzzle.
+    pub fn parse(s: &str) -> Result<Self> {
+        Self::parse_moves(s).map(Self::with_history)
+    }
+
+    fn parse_moves(s: &str) -> Result<Self> {
+        if let Ok(save) = serde_yaml::from_str::<SaveGame>(s) {
+            if save.version > SAVE_VERSION {
+                bail!("Save version {} is newer than {SAVE_VERSION}", save.version)
+            }
+            return Ok(save);
+        }
+        if !s.lines().next().is_some_and(|l| l.contains('#')) {
+            let board: Board = s.parse().map_err(anyhow::Error::msg)?;
+            return Ok(Self::new(&board, None));
+        }
+        let mut lines = s.lines();
+        let board: Board = lines
+            .next()
+            .unwrap()
+            .parse()
+            .map_err(anyhow::Error::msg)?;
+        let mut save = Self::new(&board, None);
+        for l in lines.filter(|l| !l.is_empty()) {
+            if l.starts_with(['0', '1']) {
+                save.moves.push(PlayerMove::parse(l)?);
+            } else {
+                save.completed = true;
+                save.solution = Some(l.to_string());
+            }
+        }
+        Ok(save)
+    }
+
+    /// Saves without a history get their moves as a single line, the others
+    /// get the moves leading to `current`.
+    fn with_history(mut self) -> Self {
+        if self.history.is_empty() {
+            let moves = std::mem::take(&mut self.moves);
+            self.current = None;
+            moves.into_iter().for_each(|m| {
+                self.push_move(m);
+            });
+        } else {
+            self.current = self.current.filter(|&c| c < self.history.len());
+            self.moves = self.line(self.current);
+        }
+        self
+    }
+
+    /// The moves from the start of the game to `node`.
+    pub fn line(&self, mut node: Option<usize>) -> Vec<PlayerMove> {
+        let mut ret = vec![];
+        while let Some(n) = node {
+            ret.push(self.history[n].player_move.clone());
+            node = self.history[n].parent;
+        }
+        ret.reverse();
+        ret
+    }
+
+    pub fn children(&self, node: Option<usize>) -> impl Iterator<Item = usize> + '_ {
+        (0..self.history.len()).filter(move |&i| self.history[i].parent == node)
+    }
+
+    /// Nodes no move follows, the ends of every line the player tried.
+    pub fn tips(&self) -> Vec<usize> {
+        (0..self.history.len())
+            .filter(|&i| self.children(Some(i)).next().is_none())
+            .collect()
+    }
+
+    /// Plays `m` after the current move, reusing the node when the player
+    /// tried the same move there before.
+    pub fn push_move(&mut self, m: PlayerMove) -> usize {
+        let tried = self
+            .children(self.current)
+            .find(|&i| self.history[i].player_move.same(&m));
+        let node = match tried {
+            Some(i) => i,
+            None => {
+                self.history.push(HistoryNode {
+                    parent: self.current,
+                    player_move: m.clone(),
+                });
+                self.history.len() - 1
+            }
+        };
+        self.current = Some(node);
+        self.moves.push(m);
+        node
+    }
+
+    /// Steps back to the previous move, returning the node left.
+    pub fn pop_move(&mut self) -> Option<usize> {
+        let node = self.current?;
+        self.current = self.history[node].parent;
+        self.moves.pop();
+        Some(node)
+    }
+
+    pub fn load(file: &str) -> Result<Self> {
+        let mut save = Self::parse(&std::fs::read_to_string(file)?)?;
+        if save.source.is_none() && !file.ends_with(".sol.txt") {
+            save.source = Some(file.to_string());
+        }
+        Ok(save)
+    }
+
+    pub fn write(&mut self, file: &str) -> Result<()> {
+        self.version = SAVE_VERSION;
+        self.saved = now();
+        std::fs::write(file, serde_yaml::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// The puzzle with the player moves applied, without solver deductions.
+    pub fn board(&self) -> Result<Board> {
+        let mut board: Board = self.puzzle.parse().map_err(anyhow::Error::msg)?;
+        for m in &self.moves {
+            board.clear(m.edge());
+            if !m.clear {
+                board.play(m.edge(), m.value, MoveSource::Player);
+            }
+        }
+        Ok(board)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn check_save_path() {
+        assert_eq!(save_path("puzzle/a.txt"), "puzzle/a.sol.txt");
+        assert_eq!(save_path("a.sol.txt"), "a.sol.txt");
+        assert_eq!(save_path("p.grid"), "p.sol.txt");
+        assert_eq!(save_path("5#123"), "5#123.sol.txt");
+    }
+    #[test]
+    fn check_save_game() {
+        let legacy = "4#1  0    1 21 23 \n0 0 0 n\n1 3 2 y\nnynnnnnnnnynnynnnnynnyynnnyynnnynynnnyyn";
+        let save = SaveGame::parse(legacy).unwrap();
+        assert_eq!(save.puzzle, "4#1  0    1 21 23 ");
+        assert_eq!(save.moves.len(), 2);
+        assert_eq!(save.moves[1].to_string(), "1 3 2 y");
+        assert!(save.completed);
+        assert_eq!(save.history.len(), 2);
+        assert_eq!(save.current, Some(1));
+
+        let mut save = SaveGame::parse("2#32  ").unwrap();
+        save.push_move(PlayerMove::parse("0 0 0 y").unwrap());
+        save.elapsed = 42;
+        save.checkpoints = vec![None, Some(0)];
+        let text = serde_yaml::to_string(&save).unwrap();
+        assert_eq!(SaveGame::parse(&text).unwrap(), save);
+        assert_eq!(save.board().unwrap().moves().len(), 1);
+        save.push_move(PlayerMove::parse("0 0 0 .").unwrap());
+        assert_eq!(save.moves[1].to_string(), "0 0 0 .");
+        let text = serde_yaml::to_string(&save).unwrap();
+        assert_eq!(SaveGame::parse(&text).unwrap(), save);
+        assert!(save.board().unwrap().moves().is_empty());
+
+        assert!(text.contains("checkpoints:\n- null\n- 0\n"));
+
+        assert!(text.starts_with("version: 3\n"));
+        let v1 = SaveGame::parse(&text.replace("version: 3", "version: 1")).unwrap();
+        assert_eq!(v1.version, 1);
+        assert_eq!(v1.moves, save.moves);
+        assert_eq!(v1.checkpoints, save.checkpoints);
+        assert!(SaveGame::parse(&text.replace("version: 3", "version: 4")).is_err());
+        assert!(SaveGame::parse(&text.replace("version: 3", "version: 99")).is_err());
+        assert!(PlayerMove::parse("2 0 0 y").is_err());
+        assert!(PlayerMove::parse("0 0 y").is_err());
+    }
+}
@@ -0,0 +1,978 @@
+use crate::{
+    board::{Move, MoveSource, Violation},
+    grade::MAX_GUESSES,
+    rules::BoardRule,
+    save::{self, Assist},
+    solver::{self, Conflict},
+    Board, Edge, FencesSolver, PlayerMove, SaveGame,
+};
+use anyhow::Result;
+use colored::Colorize;
+use std::{
+    io::{BufRead, Write},
+    time::Instant,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameCommand {
+    Play(PlayerMove),
+    /// Sets the edge, or clears it when it already has that value.
+    Toggle(Edge, bool),
+    /// Takes back the player move that decided the edge.
+    Clear(Edge),
+    /// Steps back along the current line of moves.
+    Undo,
+    /// Steps forward again, along the line last undone.
+    Redo,
+    /// Goes to a node of the undo tree, see [`SaveGame::history`].
+    Jump(usize),
+    /// Marks where the game is as a checkpoint, see
+    /// [`SaveGame::checkpoints`].
+    Checkpoint,
+    /// Forgets every checkpoint.
+    ClearCheckpoints,
+    /// Goes back to the last checkpoint and drops it.
+    PopCheckpoint,
+    /// Goes back to checkpoint `n`, counting from 1, and keeps it.
+    Restore(usize),
+    /// Changes how much the game plays after each move, redoing the
+    /// deductions of the moves made so far.
+    Assist(Assist),
+    /// Compares the board with the solution, undoing the moves from the
+    /// first wrong one on when `revert` is set.
+    Check {
+        revert: bool,
+    },
+    Save,
+    /// Saves and ends the game.
+    Quit,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameEvent {
+    /// A player move, with the number of edges the solver deduced from it.
+    Moved {
+        player: PlayerMove,
+        deductions: usize,
+    },
+    Cleared(PlayerMove),
+    Undone(PlayerMove),
+    Redone(PlayerMove),
+    Jumped(usize),
+    /// The checkpoints after one was added or they were cleared.
+    Checkpoints(Vec<Option<usize>>),
+    /// Back at a checkpoint, `None` being the start.
+    Restored(Option<usize>),
+    Assisted(Assist),
+    Checked(CheckReport),
+    /// The command changed nothing, for the given reason.
+    Ignored(&'static str),
+    Saved(String),
+    Won,
+    /// The board breaks a rule of the game, in these places.
+    Mistake(Vec<Violation>),
+    /// The solver found the moves contradict each other.
+    Contradiction(Conflict),
+    Quit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameState {
+    Playing,
+    Won,
+    Quit,
+}
+
+/// How the board compares with the solution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckReport {
+    /// The first player move, counting from 1, from which on the board
+    /// went wrong.
+    pub first: Option<(usize, PlayerMove)>,
+    /// Every wrong edge, with what decided it.
+    pub wrong: Vec<Move>,
+    /// Player moves undone to get back to a correct board.
+    pub reverted: usize,
+}
+
+/// A line of moves in the undo tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Branch {
+    /// The last move of the line.
+    pub tip: usize,
+    pub moves: usize,
+    /// Moves it has in common with the current line.
+    pub shared: usize,
+}
+
+/// A game being played: the player moves, with the solver run after each.
+pub struct GameSession<'a> {
+    save: SaveGame,
+    board: Board,
+    /// Nodes undone, the last one first.
+    redo: Vec<usize>,
+    rules: &'a [BoardRule],
+    sol_file: Option<String>,
+    /// Whether the solver looked for the solution already.
+    searched: bool,
+    /// Where the solver last ran into the player's moves.
+    conflict: Option<Conflict>,
+    state: GameState,
+    start: Instant,
+    /// Seconds played before this session.
+    elapsed: u64,
+}
+
+impl<'a> GameSession<'a> {
+    /// Resumes `save`, writing it back to `sol_file` when there is one.
+    pub fn new(save: SaveGame, rules: &'a [BoardRule], sol_file: Option<String>) -> Result<Self> {
+        let mut session = GameSession {
+            board: save.puzzle.parse().map_err(anyhow::Error::msg)?,
+            elapsed: save.elapsed,
+            save,
+            redo: vec![],
+            rules,
+            sol_file,
+            searched: false,
+            conflict: None,
+            state: GameState::Playing,
+            start: Instant::now(),
+        };
+        session.replay();
+        if session.save.version < 3 {
+            session.convert_checkpoints();
+        }
+        if session.board.result() == Some(true) {
+            session.win()?;
+        }
+        Ok(session)
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn save(&self) -> &SaveGame {
+        &self.save
+    }
+
+    pub fn state(&self) -> GameState {
+        self.state
+    }
+
+    /// Every line of moves the player tried.
+    pub fn branches(&self) -> Vec<Branch> {
+        self.save
+            .tips()
+            .into_iter()
+            .map(|tip| {
+                let line = self.save.line(Some(tip));
+                Branch {
+                    tip,
+                    moves: line.len(),
+                    shared: line
+                        .iter()
+                        .zip(&self.save.moves)
+                        .take_while(|(a, b)| a.same(b))
+                        .count(),
+                }
+            })
+            .collect()
+    }
+
+    /// The solution the save was completed with, or else the one the solver
+    /// finds, if it is unique. The solver runs once, and what it finds is
+    /// kept in the save.
+    pub fn solution(&mut self) -> Option<&str> {
+        if self.save.solution.is_none() && !self.searched {
+            self.searched = true;
+            self.save.solution = self.find_solution();
+        }
+        self.save.solution.as_deref()
+    }
+
+    fn find_solution(&self) -> Option<String> {
+        let mut board = Board::new(self.board.tasks().clone());
+        solver::solve_with(&mut board, self.rules).ok()?;
+        match board.result() {
+            Some(true) => Some(board.solution()),
+            Some(false) => None,
+            None => match solver::search(&board, self.rules, 2, MAX_GUESSES) {
+                res if res.complete && res.solutions.len() == 1 => {
+                    Some(res.solutions[0].solution())
+                }
+                _ => None,
+            },
+        }
+    }
+
+    /// What followed from player move `n` of the current line, counting
+    /// from 1, empty once a later move replaced it.
+    pub fn deductions(&self, n: usize) -> &[Move] {
+        let Some(m) = n.checked_sub(1).and_then(|i| self.save.moves.get(i)) else {
+            return &[];
+        };
+        let edge = m.edge();
+        if m.clear || self.save.moves[n..].iter().any(|p| p.edge() == edge) {
+            return &[];
+        }
+        match self.board.player_moves().position(|(_, p)| p.edge == edge) {
+            Some(k) => self.board.deductions(k),
+            None => &[],
+        }
+    }
+
+    /// Compares the board with the solution, `None` when it is not known.
+    pub fn check(&mut self) -> Option<CheckReport> {
+        let solution = self.solution()?.to_string();
+        let mistakes: Vec<_> = self.board.mistakes(&solution).collect();
+        // A wrong edge is blamed on the player move that played it or that
+        // it followed from, and that on the last save move playing that edge.
+        let first = mistakes
+            .iter()
+            .filter_map(|&(i, _)| {
+                let (_, m) = self
+                    .board
+                    .player_moves()
+                    .take_while(|&(k, _)| k <= i)
+                    .last()?;
+                self.save
+                    .moves
+                    .iter()
+                    .rposition(|p| !p.clear && p.edge() == m.edge)
+            })
+            .min()
+            .map(|n| (n + 1, self.save.moves[n].clone()));
+        Some(CheckReport {
+            first,
+            wrong: mistakes.into_iter().map(|(_, m)| m.clone()).collect(),
+            reverted: 0,
+        })
+    }
+
+    pub fn handle(&mut self, command: GameCommand) -> Result<Vec<GameEvent>> {
+        let mut events = vec![];
+        match command {
+            GameCommand::Play(m) => events.push(self.apply(m)),
+            GameCommand::Toggle(e @ Edge(direction, row, col), value) => {
+                if **self.board.edge(e) != Some(value) {
+                    return self.handle(GameCommand::Play(PlayerMove {
+                        direction,
+                        row,
+                        col,
+                        value,
+                        clear: false,
+                        time: save::now(),
+                    }));
+                }
+                return self.handle(GameCommand::Clear(e));
+            }
+            GameCommand::Clear(e @ Edge(direction, row, col)) => {
+                let by = self.board.moves().iter().rfind(|m| m.edge == e);
+                events.push(match by {
+                    Some(m) if m.source == MoveSource::Player => self.apply(PlayerMove {
+                        direction,
+                        row,
+                        col,
+                        value: m.value,
+                        clear: true,
+                        time: save::now(),
+                    }),
+                    Some(_) => GameEvent::Ignored("That edge follows from your other moves"),
+                    None => GameEvent::Ignored("That edge is not decided"),
+                })
+            }
+            GameCommand::Undo => events.push(match self.save.pop_move() {
+                Some(node) => {
+                    let m = &self.save.history[node].player_move;
+                    let edge = (m.direction, m.row, m.col);
+                    // A move that erased another one can't simply be taken
+                    // off the end of the board.
+                    if m.clear
+                        || self
+                            .save
+                            .moves
+                            .iter()
+                            .any(|p| (p.direction, p.row, p.col) == edge)
+                    {
+                        self.replay();
+                    } else {
+                        self.board.undo_player_move();
+                        // Brings back any deduction the move overrode.
+                        self.assist();
+                    }
+                    self.redo.push(node);
+                    GameEvent::Undone(self.save.history[node].player_move.clone())
+                }
+                None => GameEvent::Ignored("Nothing to undo"),
+            }),
+            GameCommand::Redo => {
+                // Back along the undone line, or else the newest one tried.
+                let node = match self.redo.pop() {
+                    Some(n) if self.save.history[n].parent == self.save.current => Some(n),
+                    _ => {
+                        self.redo.clear();
+                        self.save.children(self.save.current).last()
+                    }
+                };
+                events.push(match node {
+                    Some(n) => {
+                        let m = self.save.history[n].player_move.clone();
+                        self.apply(m.clone());
+                        GameEvent::Redone(m)
+                    }
+                    None => GameEvent::Ignored("Nothing to redo"),
+                })
+            }
+            GameCommand::Jump(node) if node >= self.save.history.len() => {
+                events.push(GameEvent::Ignored("There is no such move"))
+            }
+            GameCommand::Jump(node) => {
+                self.go_to(Some(node));
+                events.push(GameEvent::Jumped(node))
+            }
+            GameCommand::Checkpoint => {
+                self.save.checkpoints.push(self.save.current);
+                events.push(GameEvent::Checkpoints(self.save.checkpoints.clone()))
+            }
+            GameCommand::ClearCheckpoints => {
+                self.save.checkpoints.clear();
+                events.push(GameEvent::Checkpoints(vec![]))
+            }
+            GameCommand::PopCheckpoint => events.push(match self.save.checkpoints.pop() {
+                Some(node) => {
+                    self.go_to(node);
+                    GameEvent::Restored(node)
+                }
+                None => GameEvent::Ignored("There are no checkpoints"),
+            }),
+            GameCommand::Restore(n) => events.push(
+                match n.checked_sub(1).and_then(|i| self.save.checkpoints.get(i)) {
+                    Some(&node) => {
+                        self.go_to(node);
+                        GameEvent::Restored(node)
+                    }
+                    None => GameEvent::Ignored("There is no such checkpoint"),
+                },
+            ),
+            GameCommand::Assist(assist) => {
+                self.save.assist = assist;
+                self.replay();
+                events.push(GameEvent::Assisted(assist))
+            }
+            GameCommand::Check { revert } => events.push(match self.check() {
+                Some(mut report) => {
+                    if let (true, Some((n, _))) = (revert, &report.first) {
+                        while self.save.moves.len() >= *n {
+                            self.handle(GameCommand::Undo)?;
+                            report.reverted += 1;
+                        }
+                    }
+                    GameEvent::Checked(report)
+                }
+                None => GameEvent::Ignored("The solution is not known"),
+            }),
+            GameCommand::Save => events.push(self.write()?),
+            GameCommand::Quit => {
+                if self.sol_file.is_some() {
+                    events.push(self.write()?);
+                }
+                self.state = GameState::Quit;
+                events.push(GameEvent::Quit);
+                return Ok(events);
+            }
+        }
+        match self.board.result() {
+            Some(true) if self.state == GameState::Playing => events.extend(self.win()?),
+            Some(false) => events.push(GameEvent::Mistake(self.board.validate())),
+            _ => (),
+        }
+        if let Some(c) = &self.conflict {
+            events.push(GameEvent::Contradiction(c.clone()));
+        }
+        Ok(events)
+    }
+
+    /// Ends the game won, keeping the solution in the save.
+    fn win(&mut self) -> Result<Vec<GameEvent>> {
+        let mut events = vec![];
+        self.state = GameState::Won;
+        if !self.save.completed {
+            self.save.completed = true;
+            self.save.solution = Some(self.board.solution());
+            if self.sol_file.is_some() {
+                events.push(self.write()?);
+            }
+        }
+        events.push(GameEvent::Won);
+        Ok(events)
+    }
+
+    fn write(&mut self) -> Result<GameEvent> {
+        let Some(file) = self.sol_file.clone() else {
+            return Ok(GameEvent::Ignored("There is no save file"));
+        };
+        self.save.elapsed = self.elapsed + self.start.elapsed().as_secs();
+        self.save.write(&file)?;
+        Ok(GameEvent::Saved(file))
+    }
+
+    /// Goes to `node` of the undo tree, `None` being the start.
+    fn go_to(&mut self, node: Option<usize>) {
+        self.save.moves = self.save.line(node);
+        self.redo.clear();
+        self.replay();
+    }
+
+    /// Version 1 saves kept the number of board moves at each checkpoint,
+    /// which is turned into the node of the player moves made by then.
+    fn convert_checkpoints(&mut self) {
+        let mut nodes = vec![];
+        let mut node = self.save.current;
+        while let Some(n) = node {
+            nodes.push(n);
+            node = self.save.history[n].parent;
+        }
+        nodes.reverse();
+        let starts: Vec<_> = self.board.player_moves().map(|(i, _)| i).collect();
+        for cp in &mut self.save.checkpoints {
+            let played = starts.iter().filter(|&&i| i < cp.unwrap_or(0)).count();
+            *cp = played.checked_sub(1).and_then(|k| nodes.get(k).copied());
+        }
+        self.save.version = save::SAVE_VERSION;
+    }
+
+    /// Rebuilds the board from the puzzle and the player moves.
+    fn replay(&mut self) {
+        let moves = std::mem::take(&mut self.save.moves);
+        self.save.current = None;
+        self.board = Board::new(self.board.tasks().clone());
+        self.assist();
+        moves.into_iter().for_each(|m| {
+            self.apply(m);
+        });
+    }
+
+    /// Plays or clears an edge, replacing whatever decided it before.
+    fn apply(&mut self, m: PlayerMove) -> GameEvent {
+        self.board.clear(m.edge());
+        let before = self.board.moves().len();
+        if !m.clear {
+            self.board.play(m.edge(), m.value, MoveSource::Player);
+        }
+        self.assist();
+        self.save.push_move(m.clone());
+        if m.clear {
+            return GameEvent::Cleared(m);
+        }
+        GameEvent::Moved {
+            player: m,
+            deductions: self.board.moves().len().saturating_sub(before + 1),
+        }
+    }
+
+    /// Plays what follows from the board at the save's assist level.
+    fn assist(&mut self) {
+        self.conflict = None;
+        match self.save.assist {
+            Assist::None => (),
+            Assist::Trivial => {
+                solver::solve_trivial(&mut self.board);
+            }
+            Assist::Rules => {
+                self.conflict = solver::solve_with(&mut self.board, self.rules).err();
+            }
+            Assist::Search => {
+                self.conflict = solver::solve_with(&mut self.board, self.rules).err();
+                // Filling in the solution waits for the player to start.
+                if self.conflict.is_some()
+                    || self.board.result().is_some()
+                    || self.board.player_moves().next().is_none()
+                {
+                    return;
+                }
+                let Some(solution) = self.solution().map(str::to_string) else {
+                    return;
+                };
+                let edges: Vec<_> = self
+                    .board
+                    .fences_iter()
+                    .zip(solution.bytes())
+                    .map(|((e, f), x)| (e, **f, x == b'y'))
+                    .collect();
+                // A wrong move leaves the solution out of reach.
+                if edges.iter().any(|&(_, f, x)| f.is_some_and(|f| f != x)) {
+                    return;
+                }
+                for (e, _, value) in edges.into_iter().filter(|(_, f, _)| f.is_none()) {
+                    self.board.play(e, value, MoveSource::Search);
+                }
+            }
+        }
+    }
+}
+
+const REPL_HELP: &str = "Moves are `direction row col y|n`, e.g. `0 3 2 y`, `.` clears.
+u undo, y redo, b list branches, j <move> jump to a branch,
+c add a checkpoint, cc clear them, cp go back to the last one and drop it,
+C list them, r <n> go back to checkpoint n,
+m list moves, d <n> list what follows from your move n,
+a <none|trivial|rules|search> set how much the game plays for you,
+k [all|revert] find the first wrong move, list every wrong edge or go back
+to before the first wrong move,
+p print the board, s save, q quit";
+
+/// The checkpoints as `C` lists them, numbered for `r`.
+fn checkpoints(cps: &[Option<usize>]) -> String {
+    if cps.is_empty() {
+        return "No checkpoints".to_string();
+    }
+    cps.iter()
+        .enumerate()
+        .map(|(i, cp)| match cp {
+            Some(node) => format!("{}: at move {node}", i + 1),
+            None => format!("{}: at the start", i + 1),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Plays a session over text: reads commands from `input`, one per line,
+/// and writes the board and what happened to `out`. Ends on `q`, a win or
+/// the end of `input`.
+pub fn repl(
+    session: &mut GameSession,
+    input: impl BufRead,
+    out: &mut impl Write,
+) -> Result<GameState> {
+    writeln!(
+        out,
+        "\nMove {}:\n{}",
+        session.save.moves.len(),
+        session.board
+    )?;
+    if session.state == GameState::Won {
+        return Ok(GameState::Won);
+    }
+    let mut lines = input.lines();
+    loop {
+        writeln!(out, "Your Move:")?;
+        let Some(line) = lines.next().transpose()? else {
+            session.handle(GameCommand::Quit)?;
+            return Ok(GameState::Quit);
+        };
+        log::trace!("{line}");
+        let arg = line.split_whitespace().nth(1);
+        let command = match line.split_whitespace().next() {
+            Some("s") => GameCommand::Save,
+            Some("q") => GameCommand::Quit,
+            Some("u") => GameCommand::Undo,
+            Some("y") => GameCommand::Redo,
+            Some("k") => GameCommand::Check {
+                revert: arg == Some("revert"),
+            },
+            Some("c") => GameCommand::Checkpoint,
+            Some("cc") => GameCommand::ClearCheckpoints,
+            Some("cp") => GameCommand::PopCheckpoint,
+            Some("C") => {
+                writeln!(out, "{}", checkpoints(&session.save.checkpoints))?;
+                continue;
+            }
+            Some("r") => match arg.map(str::parse) {
+                Some(Ok(n)) => GameCommand::Restore(n),
+                _ => {
+                    writeln!(out, "r needs the checkpoint to go back to, see `C`")?;
+                    continue;
+                }
+            },
+            Some("m") => {
+                for m in session.board.moves() {
+                    writeln!(
+                        out,
+                        "[{}]={} by {}",
+                        m.edge,
+                        m.value,
+                        m.cause(session.rules)
+                    )?;
+                }
+                let moves: Vec<_> = session.save.moves.iter().map(|m| m.to_string()).collect();
+                writeln!(out, "User Moves: {}", moves.join("\n"))?;
+                continue;
+            }
+            Some("b") => {
+                for b in session.branches() {
+                    writeln!(
+                        out,
+                        "{}branch at move {}: {} moves, {} in common with yours",
+                        if b.shared == b.moves && b.moves == session.save.moves.len() {
+                            "* "
+                        } else {
+                            "  "
+                        },
+                        b.tip,
+                        b.moves,
+                        b.shared
+                    )?;
+                }
+                continue;
+            }
+            Some("j") => match line.split_whitespace().nth(1).map(str::parse) {
+                Some(Ok(node)) => GameCommand::Jump(node),
+                _ => {
+                    writeln!(out, "Jump needs the move to jump to, see `b`")?;
+                    continue;
+                }
+            },
+            Some("d") => {
+                match line.split_whitespace().nth(1).map(str::parse::<usize>) {
+                    Some(Ok(n)) if (1..=session.save.moves.len()).contains(&n) => {
+                        let deductions = session.deductions(n);
+                        writeln!(out, "{} edges follow from move {n}", deductions.len())?;
+                        for m in deductions {
+                            writeln!(out, "[{}]={} by {}", m.edge, m.value, m.source)?;
+                        }
+                    }
+                    _ => writeln!(
+                        out,
+                        "d needs one of your moves, 1 to {}",
+                        session.save.moves.len()
+                    )?,
+                }
+                continue;
+            }
+            Some("a") => match line.split_whitespace().nth(1).map(str::parse) {
+                Some(Ok(assist)) => GameCommand::Assist(assist),
+                Some(Err(e)) => {
+                    writeln!(out, "{e}")?;
+                    continue;
+                }
+                None => {
+                    writeln!(out, "Assist level: {}", session.save.assist)?;
+                    continue;
+                }
+            },
+            Some("p") => {
+                writeln!(out, "Board:\n{}", session.board)?;
+                continue;
+            }
+            Some("0" | "1") => match PlayerMove::parse(&line) {
+                Ok(m) if m.clear => GameCommand::Clear(m.edge()),
+                Ok(m) => GameCommand::Play(PlayerMove {
+                    time: save::now(),
+                    ..m
+                }),
+                Err(e) => {
+                    writeln!(out, "Wrong Input: {e}")?;
+                    continue;
+                }
+            },
+            _ => {
+                writeln!(out, "{REPL_HELP}")?;
+                continue;
+            }
+        };
+        for event in session.handle(command)? {
+            match event {
+                GameEvent::Moved { player, .. } | GameEvent::Redone(player) => writeln!(
+                    out,
+                    "Move {}: {player}\n{}",
+                    session.save.moves.len(),
+                    session.board
+                )?,
+                GameEvent::Checkpoints(cps) => writeln!(out, "{}", checkpoints(&cps))?,
+                GameEvent::Restored(_) => writeln!(
+                    out,
+                    "Back at the checkpoint, {} moves in\n{}",
+                    session.save.moves.len(),
+                    session.board
+                )?,
+                GameEvent::Jumped(node) => writeln!(
+                    out,
+                    "Back at move {node}, {} moves in\n{}",
+                    session.save.moves.len(),
+                    session.board
+                )?,
+                GameEvent::Cleared(m) => writeln!(
+                    out,
+                    "Move {}: cleared {} {} {}\n{}",
+                    session.save.moves.len(),
+                    m.direction,
+                    m.row,
+                    m.col,
+                    session.board
+                )?,
+                GameEvent::Undone(m) => writeln!(out, "undo: {m}\n{}", session.board)?,
+                GameEvent::Assisted(assist) => {
+                    writeln!(out, "Assist level: {assist}\n{}", session.board)?
+                }
+                GameEvent::Ignored(why) => writeln!(out, "{why}")?,
+                GameEvent::Saved(file) => writeln!(out, "Saved to `{file}`")?,
+                GameEvent::Won => {
+                    writeln!(out, "You completed the puzzle.")?;
+                    if let Some(file) = &session.sol_file {
+                        writeln!(out, "Checkout your moves at `{file}`!!!")?;
+                    }
+                    return Ok(GameState::Won);
+                }
+                GameEvent::Checked(report) => {
+                    match &report.first {
+                        _ if report.wrong.is_empty() => writeln!(out, "No mistakes so far")?,
+                        Some((n, m)) => writeln!(
+                            out,
+                            "Move {n}: {m} went wrong, {} edges are wrong",
+                            report.wrong.len()
+                        )?,
+                        None => writeln!(out, "{} edges are wrong", report.wrong.len())?,
+                    }
+                    if arg == Some("all") {
+                        for m in &report.wrong {
+                            writeln!(out, "[{}]={} by {}", m.edge, m.value, m.source)?;
+                        }
+                    }
+                    if report.reverted > 0 {
+                        writeln!(
+                            out,
+                            "Went back {} moves\n{}",
+                            report.reverted, session.board
+                        )?;
+                    }
+                }
+                GameEvent::Contradiction(c) => writeln!(
+                    out,
+                    "{}",
+                    format!("Your moves contradict each other: {c}").red()
+                )?,
+                GameEvent::Mistake(violations) => {
+                    writeln!(out, "{}", "You made a mistake, `k` finds it".red())?;
+                    for v in violations {
+                        writeln!(out, "  {v}")?;
+                    }
+                }
+                GameEvent::Quit => {
+                    writeln!(out, "Exiting...")?;
+                    return Ok(GameState::Quit);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn check_session() {
+        let rules = BoardRule::default_rules();
+        let board: Board = "2#    ".parse().unwrap();
+        let mut session = GameSession::new(SaveGame::new(&board, None), &rules, None).unwrap();
+        session
+            .handle(GameCommand::Toggle(Edge::horizontal(0, 0), true))
+            .unwrap();
+        session
+            .handle(GameCommand::Toggle(Edge::horizontal(0, 1), false))
+            .unwrap();
+        assert!(matches!(
+            session.handle(GameCommand::Undo).unwrap()[..],
+            [GameEvent::Undone(_)]
+        ));
+        assert!(session.board().edge(Edge::horizontal(0, 1)).is_none());
+        assert!(matches!(
+            session.handle(GameCommand::Redo).unwrap()[..],
+            [GameEvent::Redone(_)]
+        ));
+        assert!(matches!(
+            session
+                .handle(GameCommand::Toggle(Edge::horizontal(0, 0), true))
+                .unwrap()[..],
+            [GameEvent::Cleared(_)]
+        ));
+        assert!(session.board().edge(Edge::horizontal(0, 0)).is_none());
+        assert_eq!(**session.board().edge(Edge::horizontal(0, 1)), Some(false));
+        assert!(matches!(
+            session
+                .handle(GameCommand::Clear(Edge::vertical(0, 0)))
+                .unwrap()[..],
+            [GameEvent::Ignored(_)]
+        ));
+
+        // Undoing the clear brings the line back, and playing elsewhere
+        // keeps the line with the clear as a branch.
+        session.handle(GameCommand::Undo).unwrap();
+        assert_eq!(**session.board().edge(Edge::horizontal(0, 0)), Some(true));
+        session
+            .handle(GameCommand::Toggle(Edge::horizontal(2, 0), true))
+            .unwrap();
+        let branches = session.branches();
+        assert_eq!(branches.len(), 2);
+        let other = branches.iter().find(|b| b.moves == 3).unwrap().tip;
+        session.handle(GameCommand::Jump(other)).unwrap();
+        assert!(session.board().edge(Edge::horizontal(0, 0)).is_none());
+        assert_eq!(**session.board().edge(Edge::horizontal(0, 1)), Some(false));
+        assert!(session.board().edge(Edge::horizontal(2, 0)).is_none());
+        assert!(session.save().moves[2].clear);
+        let save = SaveGame::parse(&serde_yaml::to_string(session.save()).unwrap()).unwrap();
+        assert_eq!(save.history, session.save().history);
+        assert_eq!(save.moves, session.save().moves);
+
+        // Two 3s side by side, solved at the prompt without any rules.
+        let board: Board = "2#33".parse().unwrap();
+        let mut session = GameSession::new(SaveGame::new(&board, None), &[], None).unwrap();
+        let input = "0 0 0 y\nbogus\n0 0 1 y\n0 1 0 y\n0 1 1 y\n1 0 0 y\n1 0 2 y\nq\n";
+        let mut out = vec![];
+        let state = repl(&mut session, input.as_bytes(), &mut out).unwrap();
+        assert_eq!(state, GameState::Won);
+        assert!(String::from_utf8(out)
+            .unwrap()
+            .contains("You completed the puzzle."));
+        assert!(session.save().completed);
+    }
+    #[test]
+    fn check_checkpoints() {
+        let rules = BoardRule::default_rules();
+        let board: Board = "2#    ".parse().unwrap();
+        let mut session = GameSession::new(SaveGame::new(&board, None), &rules, None).unwrap();
+        for command in [
+            GameCommand::Checkpoint,
+            GameCommand::Toggle(Edge::horizontal(0, 0), true),
+            GameCommand::Checkpoint,
+            GameCommand::Toggle(Edge::horizontal(0, 1), false),
+        ] {
+            session.handle(command).unwrap();
+        }
+        assert_eq!(session.save().checkpoints, [None, Some(0)]);
+        let save = SaveGame::parse(&serde_yaml::to_string(session.save()).unwrap()).unwrap();
+        assert_eq!(save.checkpoints, session.save().checkpoints);
+        assert_eq!(
+            session.handle(GameCommand::PopCheckpoint).unwrap(),
+            [GameEvent::Restored(Some(0))]
+        );
+        assert_eq!(session.save().moves.len(), 1);
+        assert_eq!(session.save().checkpoints, [None]);
+        session.handle(GameCommand::Restore(1)).unwrap();
+        assert!(session.save().moves.is_empty());
+        assert_eq!(session.save().checkpoints, [None]);
+        assert!(matches!(
+            session.handle(GameCommand::Restore(2)).unwrap()[..],
+            [GameEvent::Ignored(_)]
+        ));
+        // Redo still finds the line left.
+        session.handle(GameCommand::Redo).unwrap();
+        session.handle(GameCommand::Redo).unwrap();
+        assert_eq!(session.save().moves.len(), 2);
+
+        // Version 1 saves counted the board moves at a checkpoint.
+        let starts: Vec<_> = session.board().player_moves().map(|(i, _)| i).collect();
+        let mut save = session.save().clone();
+        save.version = 1;
+        save.checkpoints = vec![Some(0), Some(starts[1]), Some(starts[1] + 1)];
+        let session = GameSession::new(save, &rules, None).unwrap();
+        assert_eq!(session.save().checkpoints, [None, Some(0), Some(1)]);
+
+        let mut session =
+            GameSession::new(SaveGame::new(&"2#33".parse().unwrap(), None), &[], None).unwrap();
+        let input = "c\n0 0 0 y\nc\nC\ncp\nr 1\ncc\nC\nq\n";
+        let mut out = vec![];
+        repl(&mut session, input.as_bytes(), &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("1: at the start\n2: at move 0\n"));
+        assert!(out.contains("No checkpoints"));
+        assert!(session.save().moves.is_empty());
+    }
+    #[test]
+    fn check_mistakes() {
+        let rules = BoardRule::default_rules();
+        let mut save = SaveGame::new(&"2#33".parse().unwrap(), None);
+        save.assist = Assist::None;
+        let mut session = GameSession::new(save, &rules, None).unwrap();
+        for (e, value) in [
+            (Edge::horizontal(0, 0), true),
+            (Edge::vertical(0, 1), true),
+            (Edge::horizontal(1, 0), true),
+        ] {
+            session.handle(GameCommand::Toggle(e, value)).unwrap();
+        }
+        let report = session.check().unwrap();
+        assert_eq!(report.first.unwrap().0, 2);
+        assert_eq!(report.wrong.len(), 1);
+        let events = session.handle(GameCommand::Check { revert: true }).unwrap();
+        assert!(matches!(&events[..], [GameEvent::Checked(r)] if r.reverted == 2));
+        assert_eq!(session.save().moves.len(), 1);
+        assert!(session.check().unwrap().wrong.is_empty());
+
+        // A line played and cleared before the mistake isn't blamed.
+        let mut save = SaveGame::new(&"2#33".parse().unwrap(), None);
+        save.assist = Assist::None;
+        let mut session = GameSession::new(save, &rules, None).unwrap();
+        let commands = [
+            GameCommand::Toggle(Edge::horizontal(0, 0), true),
+            GameCommand::Toggle(Edge::horizontal(1, 0), true),
+            GameCommand::Clear(Edge::horizontal(1, 0)),
+            GameCommand::Toggle(Edge::vertical(0, 1), true),
+        ];
+        for c in commands {
+            session.handle(c).unwrap();
+        }
+        let (n, m) = session.check().unwrap().first.unwrap();
+        assert_eq!((n, m.edge()), (4, Edge::vertical(0, 1)));
+        let events = session.handle(GameCommand::Check { revert: true }).unwrap();
+        assert!(matches!(&events[..], [GameEvent::Checked(r)] if r.reverted == 1));
+        assert_eq!(session.save().moves.len(), 3);
+    }
+    #[test]
+    fn check_assist() {
+        let rules = BoardRule::default_rules();
+        let board: Board = "2#0   ".parse().unwrap();
+        let mut session = GameSession::new(SaveGame::new(&board, None), &rules, None).unwrap();
+        let decided =
+            |s: &GameSession| s.board().fences_iter().filter(|(_, f)| f.is_some()).count();
+        assert!(decided(&session) > 4);
+        session.handle(GameCommand::Assist(Assist::None)).unwrap();
+        assert_eq!(decided(&session), 0);
+        session
+            .handle(GameCommand::Assist(Assist::Trivial))
+            .unwrap();
+        assert_eq!(decided(&session), 4);
+        session
+            .handle(GameCommand::Toggle(Edge::vertical(0, 2), true))
+            .unwrap();
+        session
+            .handle(GameCommand::Toggle(Edge::vertical(1, 2), true))
+            .unwrap();
+        // The dot between the two lines is complete.
+        assert_eq!(**session.board().edge(Edge::horizontal(1, 1)), Some(false));
+        assert_eq!(decided(&session), 7);
+        let save = SaveGame::parse(&serde_yaml::to_string(session.save()).unwrap()).unwrap();
+        assert_eq!(save.assist, Assist::Trivial);
+
+        // Search alone solves a puzzle with a unique solution, once the
+        // player made a move.
+        let mut save = SaveGame::new(&"2#33".parse().unwrap(), None);
+        save.assist = Assist::Search;
+        let mut session = GameSession::new(save, &[], None).unwrap();
+        assert_eq!(session.state(), GameState::Playing);
+        assert_eq!(decided(&session), 0);
+        let events = session
+            .handle(GameCommand::Toggle(Edge::horizontal(0, 0), true))
+            .unwrap();
+        assert!(events.contains(&GameEvent::Won));
+        assert_eq!(session.state(), GameState::Won);
+        assert!(session.save().completed);
+        assert!(session.save().solution.is_some());
+        // A save resumed solved is completed.
+        let mut save = session.save().clone();
+        save.completed = false;
+        let session = GameSession::new(save, &[], None).unwrap();
+        assert_eq!(session.state(), GameState::Won);
+        assert!(session.save().completed);
+        // It stays out of a board that went wrong.
+        let mut save = SaveGame::new(&"2#33".parse().unwrap(), None);
+        save.assist = Assist::None;
+        let mut session = GameSession::new(save, &[], None).unwrap();
+        session
+            .handle(GameCommand::Toggle(Edge::vertical(0, 1), true))
+            .unwrap();
+        session.handle(GameCommand::Assist(Assist::Search)).unwrap();
+        assert_eq!(session.state(), GameState::Playing);
+        assert!(session.board().edge(Edge::vertical(0, 2)).is_none());
+    }
+}
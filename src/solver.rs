@@ -1,4 +1,8 @@
-use crate::{board::*, geom::BoardGeom, *};
+use crate::{
+    board::*,
+    geom::{BoardGeom, Cell, Dot},
+    *,
+};
 use grid::Grid;
 use rules::TaskType;
 use serde::Deserialize;
@@ -162,27 +166,55 @@ impl<'de> Deserialize<'de> for BoardRules {
     }
 }
 
-pub fn solve(board: &mut impl FencesSolver) {
-    let rules = rules::BoardRule::read_rules_from_yaml("assets/rules.yml");
+/// The rules the solver uses unless given others, `assets/rules.yml` as
+/// it was at build time.
+pub const RULES: &str = include_str!("../assets/rules.yml");
+
+pub fn solve(board: &mut impl FencesSolver) -> Result<usize, Conflict> {
+    solve_with(board, &rules::BoardRule::default_rules())
+}
+
+/// Applies `rules` and closes off finished paths until nothing changes.
+/// Returns the number of rounds it took, or the first move that contradicts
+/// the board, which means the board has no solution.
+pub fn solve_with(
+    board: &mut impl FencesSolver,
+    rules: &[rules::BoardRule],
+) -> Result<usize, Conflict> {
     rules.iter().for_each(|r| log::trace!("\n{r}"));
     let keys: Vec<_> = board.tasks_iter().map(|x| x.0).collect();
     let mut hm: HashMap<_, _> = keys
         .iter()
         .map(|&k| (k, (0..rules.len()).collect::<Vec<_>>()))
         .collect();
+    let mut rounds = 0;
     loop {
         log::trace!("Solving..");
+        rounds += 1;
         let mut is_done = true;
         for &k in keys.iter() {
             if let Some(idxs) = hm.get_mut(&k) {
+                let mut conflict = None;
                 idxs.retain(|i| {
-                    if let Some(x) = rules[*i].apply_at(board, k) {
-                        is_done &= x;
-                        x
-                    } else {
-                        false
+                    if conflict.is_some() {
+                        return true;
+                    }
+                    match rules[*i].apply_at(board, k, *i) {
+                        Ok(Some(x)) => {
+                            is_done &= x;
+                            x
+                        }
+                        Ok(None) => false,
+                        Err(c) => {
+                            conflict = Some(c);
+                            true
+                        }
                     }
                 });
+                if let Some(c) = conflict {
+                    log::info!("{c}");
+                    return Err(c);
+                }
                 if idxs.is_empty() {
                     hm.remove(&k);
                 }
@@ -195,86 +227,196 @@ pub fn solve(board: &mut impl FencesSolver) {
         }
         log::trace!("{hm:?}");
     }
+    Ok(rounds)
 }
 
-pub fn block_closed_paths(board: &mut impl FencesSolver) -> bool {
+/// Crosses the edges around 0s and around dots that already have two lines,
+/// until nothing changes. Returns `true` when an edge was decided.
+pub fn solve_trivial(board: &mut impl FencesSolver) -> bool {
+    let (rows, cols) = board.size();
+    let mut crosses = vec![];
+    for (cell, t) in board.tasks_iter() {
+        if *t == Some(0) {
+            crosses.extend(board.cell_edges(cell));
+        }
+    }
     let mut changed = false;
-    let paths = board.paths();
-    if paths.len() > 1 {
-        paths.iter().for_each(|p| {
-            if p.len() < 3 {
-                return;
+    loop {
+        for e in crosses.drain(..) {
+            if board.edge(e).is_none() {
+                board.play(e, false, MoveSource::Trivial);
+                changed = true;
             }
-            let (f, l) = (p[0], p.last().unwrap());
-            if f.0 == l.0 {
-                let diff = (f.1.abs_diff(l.1), f.2.abs_diff(l.2));
-                if if f.0 == 0 {
-                    matches!(diff, (1, 0) | (0, 2))
-                } else {
-                    matches!(diff, (0, 1) | (2, 0))
-                } {
-                    let min = if f.0 == 0 { f.1.min(l.1) } else { f.2.min(l.2) };
-                    let possible_edges = if f.0 == 0 {
-                        [(0, min, (f.2 + l.2) / 2), (1, min, f.2), (1, min, f.2 + 1)]
-                    } else {
-                        [(1, (f.1 + l.1) / 2, min), (0, f.1, min), (0, f.1 + 1, min)]
-                    };
-                    possible_edges.iter().for_each(|e| {
-                        if board.edge(e.0, (e.1, e.2)).is_none() {
-                            board.play(e.0, (e.1, e.2), false, "open closed box".to_string());
-                            changed = true
-                        }
-                    })
-                }
-            } else {
-                let a = sorted_tuples(f, *l);
-                log::trace!("Link Edges: {a:?}");
-
-                for x in a.0 .0..=a.1 .0 {
-                    for y in a.0 .1..=a.1 .1 {
-                        for z in a.0 .2..=a.1 .2 {
-                            let c = (x, y, z);
-                            if !p.contains(&c)
-                                && are_linked(&f, &c)
-                                && are_linked(l, &c)
-                                && board.edge(c.0, (c.1, c.2)).is_none()
-                            {
-                                log::info!("{c:?}");
-                                board.play(x, (y, z), false, "open closed box".to_string());
-                                changed = true
-                            }
-                        }
-                    }
+        }
+        for r in 0..=rows {
+            for c in 0..=cols {
+                let edges = board.dot_edges(Dot(r, c));
+                let lines = edges
+                    .iter()
+                    .filter(|&&e| **board.edge(e) == Some(true))
+                    .count();
+                if lines == 2 {
+                    crosses.extend(edges.into_iter().filter(|&e| board.edge(e).is_none()));
                 }
             }
-        })
+        }
+        if crosses.is_empty() {
+            return changed;
+        }
     }
-    changed
 }
 
-fn sorted_tuples(a: Edge, b: Edge) -> (Edge, Edge) {
-    let mut res = (a.clone(), b.clone());
-    if a.0 > b.0 {
-        std::mem::swap(&mut res.0 .0, &mut res.1 .0)
-    };
-    if a.1 > b.1 {
-        std::mem::swap(&mut res.0 .1, &mut res.1 .1)
-    };
-    if a.2 > b.2 {
-        std::mem::swap(&mut res.0 .2, &mut res.1 .2)
+#[derive(Debug, Clone, Default)]
+pub struct SearchResult {
+    pub solutions: Vec<Board>,
+    /// Number of guesses made.
+    pub nodes: usize,
+    /// `false` when the search gave up after `max_nodes` guesses.
+    pub complete: bool,
+}
+
+/// Finds up to `limit` solutions of `board` by guessing undecided edges and
+/// propagating `rules` after every guess.
+pub fn search(
+    board: &Board,
+    rules: &[rules::BoardRule],
+    limit: usize,
+    max_nodes: usize,
+) -> SearchResult {
+    // Every guess and what follows from it is undone with a snapshot, so
+    // the board is only copied for the solutions.
+    fn go(
+        board: &mut Board,
+        rules: &[rules::BoardRule],
+        limit: usize,
+        max_nodes: usize,
+        res: &mut SearchResult,
+    ) {
+        if solve_with(board, rules).is_err() {
+            return;
+        }
+        match board.result() {
+            Some(true) => return res.solutions.push(board.clone()),
+            Some(false) => return,
+            None => (),
+        }
+        let Some((e, _)) = board.fences_iter().find(|(_, f)| f.is_none()) else {
+            return;
+        };
+        for value in [true, false] {
+            if res.solutions.len() >= limit || res.nodes >= max_nodes {
+                res.complete = false;
+                return;
+            }
+            res.nodes += 1;
+            let snapshot = board.snapshot();
+            board.play(e, value, MoveSource::Search);
+            go(board, rules, limit, max_nodes, res);
+            board.restore(snapshot);
+        }
+    }
+    let mut res = SearchResult {
+        complete: true,
+        ..Default::default()
     };
+    go(&mut board.clone(), rules, limit, max_nodes, &mut res);
+    if res.solutions.len() >= limit {
+        res.complete = false;
+    }
     res
 }
 
-pub type Edge = (usize, usize, usize);
+/// Crosses the edges that would join the two ends of a path into a loop
+/// while other paths remain. Returns `true` when an edge was decided.
+pub fn block_closed_paths(board: &mut impl FencesSolver) -> bool {
+    let mut changed = false;
+    let paths = board.paths();
+    if paths.len() > 1 {
+        for p in paths.iter().filter(|p| p.len() > 2) {
+            let (f, l) = (p[0], *p.last().unwrap());
+            let closing: Vec<_> = board
+                .edge_dots(f)
+                .into_iter()
+                .flat_map(|d| board.dot_edges(d))
+                .filter(|&e| e.is_linked(l) && !p.contains(&e) && board.edge(e).is_none())
+                .collect();
+            for e in closing {
+                log::trace!("Closing {e} would close the path from {f} to {l}");
+                board.play(e, false, MoveSource::Closure);
+                changed = true
+            }
+        }
+    }
+    changed
+}
+
+/// A state of a board to go back to: the number of moves made by then.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Snapshot(pub usize);
+
+/// What [`FencesSolver::try_play`] did to the edge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Changed {
+    /// The edge was undecided and has the value now.
+    Set,
+    /// The edge had the value already.
+    Unchanged,
+}
+
+/// A move giving an edge the other value than the one it has.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    pub edge: Edge,
+    /// The value the move wanted.
+    pub value: bool,
+    pub source: MoveSource,
+    /// The move that set the edge, `None` when it was set some other way,
+    /// e.g. by [`FencesSolver::set_solution`].
+    pub by: Option<Box<Move>>,
+}
+
+impl std::fmt::Display for Conflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = |v: bool| if v { "line" } else { "cross" };
+        write!(
+            f,
+            "{} wants a {} at edge {}, which has a {}",
+            self.source,
+            kind(self.value),
+            self.edge,
+            kind(!self.value)
+        )?;
+        match &self.by {
+            Some(m) => write!(f, " from {}", m.source),
+            None => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for Conflict {}
+
+pub use crate::geom::Edge;
 pub type Idx = (usize, usize);
 pub trait FencesSolver: BoardGeom {
     fn set_solution(&mut self, solution: &str);
     fn fences_iter(&self) -> impl Iterator<Item = (Edge, &Fence)>;
-    fn tasks_iter(&self) -> impl Iterator<Item = (Idx, &Task)>;
-    fn task(&self, idx: Idx) -> &Task;
-    fn edge(&self, dir: usize, idx: Idx) -> &Fence;
-    fn play(&mut self, dir: usize, idx: Idx, val: bool, id: String);
+    fn tasks_iter(&self) -> impl Iterator<Item = (Cell, &Task)>;
+    fn task(&self, cell: Cell) -> &Task;
+    fn edge(&self, e: Edge) -> &Fence;
+    /// Decides an edge, `source` telling what decided it. An edge that has
+    /// the other value already is left alone.
+    fn try_play(&mut self, e: Edge, val: bool, source: MoveSource) -> Result<Changed, Conflict>;
+    /// Marks the current state to go back to with [`FencesSolver::restore`].
+    fn snapshot(&self) -> Snapshot;
+    /// Undoes the moves made since `snapshot`, in time proportional to
+    /// their number.
+    fn restore(&mut self, snapshot: Snapshot);
+    /// Like [`FencesSolver::try_play`], only logging conflicts.
+    fn play(&mut self, e: Edge, val: bool, source: MoveSource) {
+        if let Err(c) = self.try_play(e, val, source) {
+            log::warn!("{c}");
+        }
+    }
     fn paths(&self) -> Vec<Vec<Edge>> {
         let mut dashes: Vec<_> = self
             .fences_iter()
@@ -291,14 +433,14 @@ pub trait FencesSolver: BoardGeom {
             let mut row_changed = false;
             if let Some(index) = dashes
                 .iter()
-                .position(|l| are_linked(l, row.front().unwrap()))
+                .position(|l| l.is_linked(*row.front().unwrap()))
             {
                 row.push_front(dashes.swap_remove(index));
                 row_changed = true;
             }
             if let Some(index) = dashes
                 .iter()
-                .position(|l| are_linked(l, row.back().unwrap()))
+                .position(|l| l.is_linked(*row.back().unwrap()))
             {
                 row.push_back(dashes.swap_remove(index));
                 row_changed = true;
@@ -322,3 +464,30 @@ pub trait FencesSolver: BoardGeom {
         ret
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn check_solve() {
+        // The 2/3/1 rule crossed the line above the 1 in `1 3 2` on the
+        // second row and left this puzzle without a solution.
+        let mut board: Board = "5#0132200132112212110221122".parse().unwrap();
+        solve(&mut board).unwrap();
+        assert_eq!(board.result(), Some(true));
+    }
+    #[test]
+    fn check_block_closed_paths() {
+        // A path from the top of cell (1, 0) up and around to the top of
+        // cell (1, 2), and a line away from it.
+        let mut board: Board =
+            "4#            \n0 1 0 y\n1 0 1 y\n0 0 1 y\n1 0 2 y\n0 1 2 y\n0 3 3 y"
+                .parse()
+                .unwrap();
+        assert!(block_closed_paths(&mut board));
+        assert_eq!(**board.edge(Edge::horizontal(1, 1)), Some(false));
+        // Only the edge between the ends closes the path, the one down from
+        // its end stays open.
+        assert!(board.edge(Edge::vertical(1, 0)).is_none());
+    }
+}
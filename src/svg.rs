@@ -0,0 +1,143 @@
+use crate::{
+    board::{sorted_paths, Tasks},
+    Board, Direction, Edge, Fences,
+};
+use std::fmt::Write;
+
+const PATH_COLORS: [&str; 6] = ["black", "green", "goldenrod", "darkcyan", "purple", "red"];
+
+#[derive(Debug, Clone)]
+pub struct SvgOptions {
+    /// Distance between two neighbouring dots, in svg user units.
+    pub cell_size: f64,
+    /// Draw `×` on edges known to be empty.
+    pub crosses: bool,
+    /// Give every path its own colour, like the coloured terminal output.
+    pub color: bool,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        Self {
+            cell_size: 40.,
+            crosses: true,
+            color: true,
+        }
+    }
+}
+
+pub fn svg_board(task: &Tasks, fences: &Fences, opts: &SvgOptions) -> String {
+    let (rows, cols) = task.size();
+    let cs = opts.cell_size;
+    let margin = cs / 2.;
+    let pos = |row: usize, col: usize| (margin + col as f64 * cs, margin + row as f64 * cs);
+    let paths = if opts.color {
+        sorted_paths(fences)
+    } else {
+        vec![]
+    };
+
+    let mut f = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">
+<rect width="100%" height="100%" fill="white"/>
+"#,
+        w = cols as f64 * cs + 2. * margin,
+        h = rows as f64 * cs + 2. * margin,
+    );
+
+    for ((row, col), t) in task.indexed_iter() {
+        if let Some(t) = t {
+            let (x, y) = pos(row, col);
+            writeln!(
+                f,
+                r#"<text x="{}" y="{}" font-family="sans-serif" font-size="{}" text-anchor="middle" dominant-baseline="central">{t}</text>"#,
+                x + cs / 2.,
+                y + cs / 2.,
+                cs * 0.6
+            )
+            .unwrap();
+        }
+    }
+
+    for (e @ Edge(dir, row, col), fence) in fences.iter() {
+        let (x1, y1) = pos(row, col);
+        let (x2, y2) = if dir == Direction::Horizontal {
+            pos(row, col + 1)
+        } else {
+            pos(row + 1, col)
+        };
+        match fence.0 {
+            Some(true) => {
+                let color = paths
+                    .iter()
+                    .position(|r| r.contains(&e))
+                    .map_or(PATH_COLORS[0], |i| PATH_COLORS[i % PATH_COLORS.len()]);
+                writeln!(
+                    f,
+                    r#"<line x1="{x1}" y1="{y1}" x2="{x2}" y2="{y2}" stroke="{color}" stroke-width="{}" stroke-linecap="round"/>"#,
+                    cs / 10.
+                )
+                .unwrap();
+            }
+            Some(false) if opts.crosses => {
+                let (cx, cy) = ((x1 + x2) / 2., (y1 + y2) / 2.);
+                let d = cs / 10.;
+                writeln!(
+                    f,
+                    r#"<path d="M{} {} L{} {} M{} {} L{} {}" stroke="gray" stroke-width="{}"/>"#,
+                    cx - d,
+                    cy - d,
+                    cx + d,
+                    cy + d,
+                    cx - d,
+                    cy + d,
+                    cx + d,
+                    cy - d,
+                    cs / 30.
+                )
+                .unwrap();
+            }
+            _ => (),
+        }
+    }
+
+    for row in 0..=rows {
+        for col in 0..=cols {
+            let (x, y) = pos(row, col);
+            writeln!(
+                f,
+                r#"<circle cx="{x}" cy="{y}" r="{}" fill="black"/>"#,
+                cs / 16.
+            )
+            .unwrap();
+        }
+    }
+    f += "</svg>\n";
+    f
+}
+
+impl Board {
+    pub fn to_svg(&self, opts: &SvgOptions) -> String {
+        svg_board(self.tasks(), self.fences(), opts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn check_svg_board() {
+        let board: Board = "2#32  \n---xx--x-x--".parse().unwrap();
+        let svg = board.to_svg(&SvgOptions::default());
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<circle").count(), 9);
+        assert_eq!(svg.matches("<line").count(), 8);
+        assert_eq!(svg.matches("<text").count(), 2);
+        assert_eq!(svg.matches("<path").count(), 4);
+        let svg = board.to_svg(&SvgOptions {
+            crosses: false,
+            ..Default::default()
+        });
+        assert_eq!(svg.matches("<path").count(), 0);
+    }
+}
@@ -0,0 +1,332 @@
+use crate::{
+    rules::BoardRule,
+    session::{GameCommand, GameEvent, GameState},
+    BoardGeom, Edge, FencesSolver, GameSession, SaveGame,
+};
+use anyhow::Result;
+use colored::Colorize;
+use crossterm::{
+    cursor::{MoveTo, MoveToNextLine, SetCursorStyle, Show},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind,
+        KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+    },
+    execute, queue,
+    style::Print,
+    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use std::io::{self, Write};
+
+/// Columns `Display for Board` puts before the first dot of a line.
+pub const RULER_WIDTH: u16 = 6;
+/// Lines `Display for Board` puts before the first line of dots.
+pub const RULER_HEIGHT: u16 = 1;
+
+/// The edge at line `i`, column `j` of `print_board`, if there is one.
+pub fn layout_edge(i: usize, j: usize) -> Option<Edge> {
+    match (i % 2, j % 2) {
+        (0, 1) => Some(Edge::horizontal(i / 2, j / 2)),
+        (1, 0) => Some(Edge::vertical(i / 2, j / 2)),
+        _ => None,
+    }
+}
+
+/// The layout position under terminal cell `(x, y)` of the board drawn with
+/// its rulers. Clicks one line or column off the board land on its border.
+pub fn mouse_layout(x: u16, y: u16, (rows, cols): (usize, usize)) -> Option<(usize, usize)> {
+    // Shifted by one so the line or column before the board is 0.
+    let snap = |v: u16, start: u16, len: usize| {
+        let v = (v as usize + 1).checked_sub(start as usize)?;
+        (v <= len + 2).then(|| v.clamp(1, len + 1) - 1)
+    };
+    Some((
+        snap(y, RULER_HEIGHT, 2 * rows)?,
+        snap(x, RULER_WIDTH, 2 * cols)?,
+    ))
+}
+
+/// The edge meant by a click at layout position `at`: the edge under it, or
+/// in a cell the side facing `from`, where the pointer came from. A cell is
+/// a single character, so that is the only offset within it there is. Dots,
+/// and cells without a `from` elsewhere, have none.
+pub fn mouse_edge((i, j): (usize, usize), from: Option<(usize, usize)>) -> Option<Edge> {
+    if i % 2 == 0 || j % 2 == 0 {
+        return layout_edge(i, j);
+    }
+    let (fi, fj) = from?;
+    let (di, dj) = (fi as isize - i as isize, fj as isize - j as isize);
+    // Lines are about twice as tall as columns are wide.
+    match (2 * di.abs()).cmp(&dj.abs()) {
+        _ if (di, dj) == (0, 0) => None,
+        std::cmp::Ordering::Less => layout_edge(i, (j as isize + dj.signum()) as usize),
+        _ => layout_edge((i as isize + di.signum()) as usize, j),
+    }
+}
+
+/// Puts the terminal back however the game ends.
+struct Screen;
+
+impl Screen {
+    fn enter() -> Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+        Ok(Screen)
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = execute!(
+            io::stdout(),
+            SetCursorStyle::DefaultUserShape,
+            DisableMouseCapture,
+            LeaveAlternateScreen
+        );
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+const HELP: &str = "arrows move  space/click line  x/right click cross  backspace clear  \
+    u undo  r redo  b next branch  a assist  k check  K go back to before a mistake  \
+    s save  q quit";
+
+fn draw(
+    out: &mut impl Write,
+    session: &GameSession,
+    cursor: (usize, usize),
+    message: &str,
+) -> Result<()> {
+    let b = session.board();
+    queue!(out, Clear(ClearType::All), MoveTo(0, 0))?;
+    for l in b.to_string().lines() {
+        queue!(out, Print(l), MoveToNextLine(1))?;
+    }
+    let total = b.fences_iter().count();
+    let decided = b.fences_iter().filter(|(_, f)| f.is_some()).count();
+    let at = layout_edge(cursor.0, cursor.1).map_or(String::new(), |e| format!("  edge {e}"));
+    let result = match b.result() {
+        Some(true) => "Solved! Press any key to leave".green().to_string(),
+        Some(false) if message.is_empty() => match b.validate().first() {
+            Some(v) => format!("You made a mistake, {v}; k finds it"),
+            None => "You made a mistake somewhere, k finds it".to_string(),
+        }
+        .red()
+        .to_string(),
+        _ => message.to_string(),
+    };
+    queue!(
+        out,
+        MoveToNextLine(1),
+        Print(format!(
+            "Move {}  {decided}/{total} edges  {} branches  assist {}{at}",
+            session.save().moves.len(),
+            session.branches().len(),
+            session.save().assist
+        )),
+        MoveToNextLine(1),
+        Print(result),
+        MoveToNextLine(1),
+        Print(HELP.dimmed()),
+        MoveTo(
+            RULER_WIDTH + cursor.1 as u16,
+            RULER_HEIGHT + cursor.0 as u16
+        ),
+        Show,
+        SetCursorStyle::SteadyBlock
+    )?;
+    out.flush()?;
+    Ok(())
+}
+
+/// What happened, for the status bar.
+fn describe(events: &[GameEvent]) -> String {
+    events
+        .iter()
+        .filter_map(|e| match e {
+            GameEvent::Moved { player, deductions } => {
+                Some(format!("Played {player}, {deductions} edges follow"))
+            }
+            GameEvent::Cleared(m) => Some(format!("Cleared {m}")),
+            GameEvent::Undone(m) => Some(format!("Undid {m}")),
+            GameEvent::Redone(m) => Some(format!("Redid {m}")),
+            GameEvent::Jumped(node) => Some(format!("Jumped to move {node}")),
+            GameEvent::Checkpoints(cps) => Some(format!("{} checkpoints", cps.len())),
+            GameEvent::Restored(_) => Some("Back at the checkpoint".to_string()),
+            GameEvent::Assisted(assist) => Some(format!("Assist level {assist}")),
+            GameEvent::Checked(report) => Some(match &report.first {
+                _ if report.wrong.is_empty() => "No mistakes so far".to_string(),
+                Some((n, m)) if report.reverted > 0 => format!(
+                    "Move {n}: {m} went wrong, went back {} moves",
+                    report.reverted
+                ),
+                Some((n, m)) => format!(
+                    "Move {n}: {m} went wrong, {} edges are wrong",
+                    report.wrong.len()
+                ),
+                None => format!("{} edges are wrong", report.wrong.len()),
+            }),
+            GameEvent::Ignored(why) => Some(why.to_string()),
+            GameEvent::Saved(file) => Some(format!("Saved to {file}")),
+            GameEvent::Contradiction(c) => Some(
+                format!("Your moves contradict each other: {c}")
+                    .red()
+                    .to_string(),
+            ),
+            GameEvent::Won | GameEvent::Mistake(_) | GameEvent::Quit => None,
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Plays `save` full screen, saving to `sol_file` on `s`, `q` and when solved.
+pub fn play_tui(save: SaveGame, sol_file: &str, rules: &[BoardRule]) -> Result<()> {
+    let mut session = GameSession::new(save, rules, Some(sol_file.to_string()))?;
+    let (rows, cols) = (2 * session.board().rows(), 2 * session.board().cols());
+    let mut cursor = (0, 1);
+    let mut message = String::new();
+    let mut drag = None;
+    let mut pointer = (None, None);
+    let _screen = Screen::enter()?;
+    let out = &mut io::stdout();
+    loop {
+        draw(out, &session, cursor, &message)?;
+        match session.state() {
+            GameState::Won => {
+                event::read()?;
+                return Ok(());
+            }
+            GameState::Quit => return Ok(()),
+            GameState::Playing => (),
+        }
+        let command = match event::read()? {
+            Event::Key(KeyEvent {
+                code,
+                modifiers,
+                kind: KeyEventKind::Press,
+                ..
+            }) => {
+                let edge = layout_edge(cursor.0, cursor.1);
+                match code {
+                    KeyCode::Up => cursor.0 = cursor.0.saturating_sub(1),
+                    KeyCode::Down => cursor.0 = (cursor.0 + 1).min(rows),
+                    KeyCode::Left => cursor.1 = cursor.1.saturating_sub(1),
+                    KeyCode::Right => cursor.1 = (cursor.1 + 1).min(cols),
+                    _ => (),
+                }
+                match (code, edge) {
+                    (KeyCode::Char(' ' | 'x' | '-') | KeyCode::Enter, Some(e)) => {
+                        GameCommand::Toggle(e, code != KeyCode::Char('x'))
+                    }
+                    (KeyCode::Char(' ' | 'x' | '-') | KeyCode::Enter, None) => {
+                        message = "Move onto an edge first".to_string();
+                        continue;
+                    }
+                    (KeyCode::Char('c'), _) if modifiers.contains(KeyModifiers::CONTROL) => {
+                        GameCommand::Quit
+                    }
+                    (KeyCode::Backspace | KeyCode::Delete | KeyCode::Char('c'), Some(e)) => {
+                        GameCommand::Clear(e)
+                    }
+                    (KeyCode::Char('u' | 'z'), _) => GameCommand::Undo,
+                    (KeyCode::Char('r' | 'y'), _) => GameCommand::Redo,
+                    (KeyCode::Char('b'), _) => {
+                        let branches = session.branches();
+                        let moves = session.save().moves.len();
+                        let Some(at) = branches.iter().position(|b| b.shared == moves) else {
+                            message = "There are no branches yet".to_string();
+                            continue;
+                        };
+                        GameCommand::Jump(branches[(at + 1) % branches.len()].tip)
+                    }
+                    (KeyCode::Char('a'), _) => GameCommand::Assist(session.save().assist.next()),
+                    (KeyCode::Char('k' | 'K'), _) => GameCommand::Check {
+                        revert: code == KeyCode::Char('K'),
+                    },
+                    (KeyCode::Char('s'), _) => GameCommand::Save,
+                    (KeyCode::Char('q') | KeyCode::Esc, _) => GameCommand::Quit,
+                    _ => {
+                        message.clear();
+                        continue;
+                    }
+                }
+            }
+            Event::Mouse(MouseEvent {
+                kind, column, row, ..
+            }) => {
+                let Some(at) = mouse_layout(column, row, session.board().size()) else {
+                    continue;
+                };
+                // The last two positions the pointer was over.
+                if pointer.1 != Some(at) {
+                    pointer = (pointer.1, Some(at));
+                }
+                let edge = mouse_edge(at, pointer.0);
+                if !matches!(kind, MouseEventKind::Moved | MouseEventKind::Up(_)) {
+                    cursor = edge.map_or(at, |Edge(dir, row, col)| {
+                        (2 * row + dir as usize, 2 * col + 1 - dir as usize)
+                    });
+                }
+                match (kind, edge) {
+                    (MouseEventKind::Down(button), Some(e)) => {
+                        let value = button != MouseButton::Right;
+                        drag = Some((e, value));
+                        GameCommand::Toggle(e, value)
+                    }
+                    // Dragging draws over every edge it passes, without
+                    // clearing the ones already drawn.
+                    (MouseEventKind::Drag(_), Some(e)) => match drag.filter(|d| d.0 != e) {
+                        Some((_, value)) => {
+                            drag = Some((e, value));
+                            if **session.board().edge(e) == Some(value) {
+                                continue;
+                            }
+                            GameCommand::Toggle(e, value)
+                        }
+                        None => continue,
+                    },
+                    (MouseEventKind::Up(_), _) => {
+                        drag = None;
+                        continue;
+                    }
+                    _ => continue,
+                }
+            }
+            _ => continue,
+        };
+        message = describe(&session.handle(command)?);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn check_layout() {
+        assert_eq!(layout_edge(0, 1), Some(Edge::horizontal(0, 0)));
+        assert_eq!(layout_edge(3, 4), Some(Edge::vertical(1, 2)));
+        assert_eq!(layout_edge(1, 1), None);
+        assert_eq!(mouse_layout(7, 1, (2, 2)), Some((0, 1)));
+        // Next to the board, onto its border.
+        assert_eq!(mouse_layout(5, 2, (2, 2)), Some((1, 0)));
+        assert_eq!(mouse_layout(7, 6, (2, 2)), Some((4, 1)));
+        assert_eq!(mouse_layout(4, 2, (2, 2)), None);
+        assert_eq!(mouse_layout(7, 7, (2, 2)), None);
+
+        assert_eq!(mouse_edge((2, 1), None), Some(Edge::horizontal(1, 0)));
+        assert_eq!(mouse_edge((2, 2), Some((1, 1))), None);
+        assert_eq!(mouse_edge((1, 1), None), None);
+        assert_eq!(mouse_edge((1, 1), Some((1, 1))), None);
+        // Cells take the side the pointer came from.
+        assert_eq!(mouse_edge((1, 1), Some((1, 0))), Some(Edge::vertical(0, 0)));
+        assert_eq!(mouse_edge((1, 3), Some((1, 5))), Some(Edge::vertical(0, 2)));
+        assert_eq!(
+            mouse_edge((3, 1), Some((1, 2))),
+            Some(Edge::horizontal(1, 0))
+        );
+        assert_eq!(
+            mouse_edge((1, 1), Some((2, 3))),
+            Some(Edge::horizontal(1, 0))
+        );
+        assert_eq!(mouse_edge((1, 1), Some((2, 4))), Some(Edge::vertical(0, 1)));
+    }
+}
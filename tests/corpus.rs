@@ -0,0 +1,92 @@
+//! Solves every puzzle under `puzzle/` and compares the outcome with
+//! `tests/expected/<same path>`, which holds the fences as given by
+//! `Board::solution`, with `.` for the edges the solver left undecided. Run
+//! with `BLESS=1` to write the current outcomes.
+
+use fences::{
+    batch::puzzle_files, formats::load_puzzle, rules::BoardRule, solver::solve_with, Board,
+    FencesSolver,
+};
+use std::path::Path;
+
+/// [`Board::solution`] with the undecided edges as `.`.
+fn outcome(board: &Board) -> String {
+    board
+        .solution()
+        .chars()
+        .zip(board.fences_iter())
+        .map(|(c, (_, f))| if f.is_some() { c } else { '.' })
+        .collect()
+}
+
+#[test]
+fn corpus() {
+    let bless = std::env::var_os("BLESS").is_some();
+    let rules = BoardRule::default_rules();
+    let mut failures = vec![];
+    for file in puzzle_files(Path::new("puzzle")).unwrap() {
+        let expected_file = Path::new("tests/expected").join(file.strip_prefix("puzzle").unwrap());
+        let mut board = load_puzzle(&std::fs::read_to_string(&file).unwrap()).unwrap();
+        if let Err(c) = solve_with(&mut board, &rules) {
+            failures.push(format!("{}: the solver ran into {c}", file.display()));
+            continue;
+        }
+        if board.result() == Some(false) {
+            failures.push(format!(
+                "{}: the solver ends in a contradiction",
+                file.display()
+            ));
+            continue;
+        }
+        let got = outcome(&board);
+        if bless {
+            std::fs::create_dir_all(expected_file.parent().unwrap()).unwrap();
+            std::fs::write(&expected_file, format!("{got}\n")).unwrap();
+            continue;
+        }
+        let Ok(expected) = std::fs::read_to_string(&expected_file) else {
+            failures.push(format!(
+                "{}: no expected outcome, run with BLESS=1",
+                file.display()
+            ));
+            continue;
+        };
+        let expected = expected.trim();
+        if expected == got {
+            continue;
+        }
+        // Edges decided before that are now undecided or the other way.
+        let wrong: Vec<_> = board
+            .fences_iter()
+            .zip(expected.chars().zip(got.chars()))
+            .filter(|(_, (a, b))| *a != '.' && a != b)
+            .map(|((e, _), (a, b))| format!("{e}: expected {a} got {b}"))
+            .collect();
+        let decided = |s: &str| s.chars().filter(|&c| c != '.').count();
+        if expected.len() != got.len() {
+            failures.push(format!(
+                "{}: expected {} edges got {}",
+                file.display(),
+                expected.len(),
+                got.len()
+            ));
+        } else if !wrong.is_empty() {
+            failures.push(format!(
+                "{}: {} edges differ, {}",
+                file.display(),
+                wrong.len(),
+                wrong[..wrong.len().min(5)].join(", ")
+            ));
+        } else if board.result() == Some(true) {
+            println!("{}: now solved, run with BLESS=1", file.display());
+        } else {
+            println!(
+                "{}: improved from {} to {} edges, run with BLESS=1",
+                file.display(),
+                decided(expected),
+                decided(&got)
+            );
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}